@@ -1,25 +1,48 @@
+//! Buffering requests to a service that runs in a background task.
+//!
+//! [`Router::boxed`](crate::Router::boxed) puts the boxed router behind an
+//! [`MpscBuffer`], so it can be cloned cheaply for every request. Use
+//! [`Builder`] and [`Router::boxed_with`](crate::Router::boxed_with) to
+//! configure the buffer.
+
+use crate::router::RouteMethods;
 use futures_util::ready;
 use pin_project_lite::pin_project;
 use std::{
+    convert::TryFrom,
+    error::Error as StdError,
+    fmt,
     future::Future,
     pin::Pin,
-    sync::Arc,
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc,
+    },
     task::{Context, Poll},
+    time::{Duration, Instant},
 };
 use tokio::sync::{mpsc, oneshot, OwnedSemaphorePermit, Semaphore};
 use tokio_util::sync::PollSemaphore;
 use tower::ServiceExt;
 use tower_service::Service;
 
-/// A version of [`tower::buffer::Buffer`] which panicks on channel related errors, thus keeping
-/// the error type of the service.
-pub(crate) struct MpscBuffer<S, R>
+/// A version of [`tower::buffer::Buffer`] which keeps the error type of the
+/// service, wrapped in [`BufferError`].
+///
+/// At most [`Builder::capacity`] requests can be in flight at once. Further
+/// requests wait for a slot, or are rejected with [`BufferError::Overloaded`]
+/// if [`Builder::load_shed`] is enabled.
+pub struct MpscBuffer<S, R>
 where
     S: Service<R>,
 {
     tx: mpsc::UnboundedSender<Msg<S, R>>,
     semaphore: PollSemaphore,
     permit: Option<OwnedSemaphorePermit>,
+    load_shed: bool,
+    // 开启负载削减并且没有空位时，`call` 直接返回错误
+    overloaded: bool,
+    metrics: BufferMetrics,
 }
 
 impl<S, R> Clone for MpscBuffer<S, R>
@@ -31,40 +54,190 @@ where
             tx: self.tx.clone(),
             semaphore: self.semaphore.clone(),
             permit: None,
+            load_shed: self.load_shed,
+            overloaded: false,
+            metrics: self.metrics.clone(),
         }
     }
 }
 
+impl<S, R> fmt::Debug for MpscBuffer<S, R>
+where
+    S: Service<R>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MpscBuffer")
+            .field("load_shed", &self.load_shed)
+            .field("metrics", &self.metrics)
+            .finish()
+    }
+}
+
 impl<S, R> MpscBuffer<S, R>
 where
     S: Service<R>,
 {
-    pub(crate) fn new(svc: S) -> Self
+    /// Create a new `MpscBuffer` with the default configuration.
+    ///
+    /// The service is spawned on the current Tokio runtime, so this must be
+    /// called from within a runtime.
+    pub fn new(svc: S) -> Self
     where
         S: Send + 'static,
         R: Send + 'static,
         S::Error: Send + 'static,
         S::Future: Send + 'static,
     {
-        let (tx, rx) = mpsc::unbounded_channel::<Msg<S, R>>();
-        let semaphore = PollSemaphore::new(Arc::new(Semaphore::new(1024)));
+        Builder::new().build(svc)
+    }
+}
 
-        tokio::spawn(run_worker(svc, rx));
+/// Builder for [`MpscBuffer`].
+///
+/// ```rust,no_run
+/// use axumlike02::{buffer::Builder, handler::get, Router};
+///
+/// # async {
+/// let buffer = Builder::new().capacity(256).load_shed(true);
+/// let metrics = buffer.metrics();
+///
+/// let app = Router::new()
+///     .route("/", get(|| async { "Hello, World!" }))
+///     .boxed_with(&buffer);
+///
+/// println!("{} requests rejected", metrics.rejected());
+/// # axumlike02::Server::bind(&"".parse().unwrap()).serve(app.into_make_service()).await.unwrap();
+/// # };
+/// ```
+#[derive(Debug, Clone)]
+pub struct Builder {
+    capacity: usize,
+    load_shed: bool,
+    metrics: BufferMetrics,
+}
 
+impl Default for Builder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Builder {
+    /// Create a new `Builder` with a capacity of 1024 requests and load
+    /// shedding disabled.
+    pub fn new() -> Self {
         Self {
+            capacity: 1024,
+            load_shed: false,
+            metrics: BufferMetrics::default(),
+        }
+    }
+
+    /// Set how many requests can be in flight at once.
+    ///
+    /// A request takes a slot when the buffer is ready to accept it and
+    /// releases it when its response future completes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is zero.
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        assert!(capacity > 0, "Buffer capacity must be greater than zero");
+        self.capacity = capacity;
+        self
+    }
+
+    /// Reject requests with [`BufferError::Overloaded`] instead of waiting
+    /// when the buffer is full.
+    pub fn load_shed(mut self, load_shed: bool) -> Self {
+        self.load_shed = load_shed;
+        self
+    }
+
+    /// The metrics of the buffers built with this builder.
+    pub fn metrics(&self) -> BufferMetrics {
+        self.metrics.clone()
+    }
+
+    /// Spawn `svc` on the current Tokio runtime and return a buffer sending
+    /// requests to it.
+    pub fn build<S, R>(&self, svc: S) -> MpscBuffer<S, R>
+    where
+        S: Service<R> + Send + 'static,
+        R: Send + 'static,
+        S::Error: Send + 'static,
+        S::Future: Send + 'static,
+    {
+        let (tx, rx) = mpsc::unbounded_channel::<Msg<S, R>>();
+        let semaphore = PollSemaphore::new(Arc::new(Semaphore::new(self.capacity)));
+
+        tokio::spawn(run_worker(svc, rx, self.metrics.clone()));
+
+        MpscBuffer {
             tx,
             semaphore,
             permit: None,
+            load_shed: self.load_shed,
+            overloaded: false,
+            metrics: self.metrics.clone(),
         }
     }
 }
 
-async fn run_worker<S, R>(mut svc: S, mut rx: mpsc::UnboundedReceiver<Msg<S, R>>)
-where
+/// Counters shared by the buffers built by a [`Builder`].
+#[derive(Debug, Clone, Default)]
+pub struct BufferMetrics {
+    inner: Arc<MetricsInner>,
+}
+
+#[derive(Debug, Default)]
+struct MetricsInner {
+    queued: AtomicUsize,
+    dispatched: AtomicU64,
+    wait_nanos: AtomicU64,
+    rejected: AtomicU64,
+}
+
+impl BufferMetrics {
+    /// The number of requests waiting for the service to be ready.
+    pub fn queue_depth(&self) -> usize {
+        self.inner.queued.load(Ordering::Relaxed)
+    }
+
+    /// The number of requests passed to the service.
+    pub fn dispatched(&self) -> u64 {
+        self.inner.dispatched.load(Ordering::Relaxed)
+    }
+
+    /// The total time requests spent waiting for the service to be ready.
+    pub fn wait_time(&self) -> Duration {
+        Duration::from_nanos(self.inner.wait_nanos.load(Ordering::Relaxed))
+    }
+
+    /// The number of requests rejected because the buffer was full.
+    pub fn rejected(&self) -> u64 {
+        self.inner.rejected.load(Ordering::Relaxed)
+    }
+}
+
+async fn run_worker<S, R>(
+    mut svc: S,
+    mut rx: mpsc::UnboundedReceiver<Msg<S, R>>,
+    metrics: BufferMetrics,
+) where
     S: Service<R>,
 {
-    while let Some((req, reply_tx)) = rx.recv().await {
-        match svc.ready().await {
+    while let Some((req, queued, reply_tx)) = rx.recv().await {
+        let ready = svc.ready().await;
+
+        let waited = u64::try_from(queued.sent_at.elapsed().as_nanos()).unwrap_or(u64::MAX);
+        drop(queued);
+
+        let metrics = &metrics.inner;
+        metrics.dispatched.fetch_add(1, Ordering::Relaxed);
+        metrics.wait_nanos.fetch_add(waited, Ordering::Relaxed);
+
+        match ready {
             Ok(svc) => {
                 let future = svc.call(req);
                 let _ = reply_tx.send(WorkerReply::Future(future));
@@ -78,36 +251,93 @@ where
 
 type Msg<S, R> = (
     R,
+    Queued,
     oneshot::Sender<WorkerReply<<S as Service<R>>::Future, <S as Service<R>>::Error>>,
 );
 
+// 在队列中的请求，离开队列时减少 `queued`。worker 退出时还没处理的请求会随着
+// 接收端一起被丢弃，这样也能被计算进去
+struct Queued {
+    sent_at: Instant,
+    metrics: BufferMetrics,
+}
+
+impl Queued {
+    fn new(metrics: &BufferMetrics) -> Self {
+        metrics.inner.queued.fetch_add(1, Ordering::Relaxed);
+        Self {
+            sent_at: Instant::now(),
+            metrics: metrics.clone(),
+        }
+    }
+}
+
+impl Drop for Queued {
+    fn drop(&mut self) {
+        self.metrics.inner.queued.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
 enum WorkerReply<F, E> {
     Future(F),
     Error(E),
 }
 
+impl<S, R> RouteMethods for MpscBuffer<S, R>
+where
+    S: Service<R>,
+{
+}
+
 impl<S, R> Service<R> for MpscBuffer<S, R>
 where
     S: Service<R>,
 {
     type Response = S::Response;
-    type Error = S::Error;
+    type Error = BufferError<S::Error>;
     type Future = ResponseFuture<S::Future, S::Error>;
 
     fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
-        if self.permit.is_some() {
+        if self.permit.is_some() || self.overloaded {
             return Poll::Ready(Ok(()));
         }
 
-        let permit = ready!(self.semaphore.poll_acquire(cx))
-            .expect("buffer semaphore closed. This is a bug in axum and should never happen. Please file an issue");
-
-        self.permit = Some(permit);
+        let permit = if self.load_shed {
+            match self.semaphore.clone_inner().try_acquire_owned() {
+                Ok(permit) => Some(permit),
+                Err(tokio::sync::TryAcquireError::NoPermits) => {
+                    self.overloaded = true;
+                    return Poll::Ready(Ok(()));
+                }
+                Err(tokio::sync::TryAcquireError::Closed) => None,
+            }
+        } else {
+            ready!(self.semaphore.poll_acquire(cx))
+        };
 
-        Poll::Ready(Ok(()))
+        // 信号量被关闭说明 buffer 已经不可用
+        match permit {
+            Some(permit) => {
+                self.permit = Some(permit);
+                Poll::Ready(Ok(()))
+            }
+            None => Poll::Ready(Err(BufferError::Closed)),
+        }
     }
 
     fn call(&mut self, req: R) -> Self::Future {
+        if self.overloaded {
+            self.overloaded = false;
+            self.metrics.inner.rejected.fetch_add(1, Ordering::Relaxed);
+
+            return ResponseFuture {
+                state: State::Failed {
+                    error: Some(BufferError::Overloaded),
+                },
+                permit: None,
+            };
+        }
+
         let permit = self
             .permit
             .take()
@@ -115,22 +345,71 @@ where
 
         let (reply_tx, reply_rx) = oneshot::channel::<WorkerReply<S::Future, S::Error>>();
 
-        self.tx.send((req, reply_tx)).unwrap_or_else(|_| {
-            panic!("buffer worker not running. This is a bug in axum and should never happen. Please file an issue")
-        });
+        let queued = Queued::new(&self.metrics);
+
+        let state = match self.tx.send((req, queued, reply_tx)) {
+            Ok(()) => State::Channel { reply_rx },
+            Err(_) => State::Failed {
+                error: Some(BufferError::Closed),
+            },
+        };
 
         ResponseFuture {
-            state: State::Channel { reply_rx },
-            permit,
+            state,
+            permit: Some(permit),
+        }
+    }
+}
+
+/// Error returned by [`MpscBuffer`].
+#[derive(Debug)]
+pub enum BufferError<E> {
+    /// The buffered service returned an error.
+    Service(E),
+    /// The buffer was full and load shedding is enabled.
+    Overloaded,
+    /// The task running the service has stopped, for example because the
+    /// service panicked or the runtime is shutting down.
+    Closed,
+}
+
+impl<E> fmt::Display for BufferError<E>
+where
+    E: fmt::Display,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Service(err) => err.fmt(f),
+            Self::Overloaded => write!(f, "Buffer is full"),
+            Self::Closed => write!(f, "Buffered service is no longer running"),
+        }
+    }
+}
+
+impl<E> StdError for BufferError<E>
+where
+    E: StdError + 'static,
+{
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            Self::Service(err) => Some(err),
+            Self::Overloaded | Self::Closed => None,
         }
     }
 }
 
 pin_project! {
-    pub(crate) struct ResponseFuture<F, E> {
+    /// Response future for [`MpscBuffer`].
+    pub struct ResponseFuture<F, E> {
         #[pin]
         state: State<F, E>,
-        permit: OwnedSemaphorePermit,
+        permit: Option<OwnedSemaphorePermit>,
+    }
+}
+
+impl<F, E> fmt::Debug for ResponseFuture<F, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ResponseFuture").finish()
     }
 }
 
@@ -139,6 +418,7 @@ pin_project! {
     enum State<F, E> {
         Channel { reply_rx: oneshot::Receiver<WorkerReply<F, E>> },
         Future { #[pin] future: F },
+        Failed { error: Option<BufferError<E>> },
     }
 }
 
@@ -146,7 +426,7 @@ impl<F, E, T> Future for ResponseFuture<F, E>
 where
     F: Future<Output = Result<T, E>>,
 {
-    type Output = Result<T, E>;
+    type Output = Result<T, BufferError<E>>;
 
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         loop {
@@ -154,20 +434,238 @@ where
 
             let new_state = match this.state.as_mut().project() {
                 StateProj::Channel { reply_rx } => {
-                    let msg = ready!(Pin::new(reply_rx).poll(cx))
-                        .expect("buffer worker not running. This is a bug in axum and should never happen. Please file an issue");
-
-                    match msg {
-                        WorkerReply::Future(future) => State::Future { future },
-                        WorkerReply::Error(err) => return Poll::Ready(Err(err)),
+                    // worker 退出时会丢弃发送端
+                    match ready!(Pin::new(reply_rx).poll(cx)) {
+                        Ok(WorkerReply::Future(future)) => State::Future { future },
+                        Ok(WorkerReply::Error(err)) => {
+                            return Poll::Ready(Err(BufferError::Service(err)))
+                        }
+                        Err(_) => return Poll::Ready(Err(BufferError::Closed)),
                     }
                 }
                 StateProj::Future { future } => {
-                    return future.poll(cx);
+                    return future.poll(cx).map_err(BufferError::Service);
+                }
+                StateProj::Failed { error } => {
+                    let error = error.take().expect("polled after completion");
+                    return Poll::Ready(Err(error));
                 }
             };
 
             this.state.set(new_state);
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{handler::get, http::StatusCode, test_client::TestClient, Router};
+    use std::convert::Infallible;
+    use tokio::sync::Notify;
+
+    // `poll_ready` 会 panic，worker 收到第一个请求后就会退出
+    struct PanicOnReady;
+
+    impl Service<()> for PanicOnReady {
+        type Response = ();
+        type Error = std::convert::Infallible;
+        type Future = std::future::Ready<Result<(), Self::Error>>;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            panic!("service failed")
+        }
+
+        fn call(&mut self, _req: ()) -> Self::Future {
+            unreachable!()
+        }
+    }
+
+    #[tokio::test]
+    async fn queue_depth_after_worker_exits() {
+        let builder = Builder::new();
+        let metrics = builder.metrics();
+        let buffer = builder.build(PanicOnReady);
+
+        // 在 worker 运行之前把三个请求都放进队列
+        let mut responses = Vec::new();
+        for _ in 0..3 {
+            let mut buffer = buffer.clone();
+            responses.push(buffer.ready().await.unwrap().call(()));
+        }
+        assert_eq!(metrics.queue_depth(), 3);
+
+        for response in responses {
+            assert!(matches!(response.await, Err(BufferError::Closed)));
+        }
+        assert_eq!(metrics.queue_depth(), 0);
+
+        // 之后的请求不会再进入队列
+        assert!(matches!(
+            buffer.clone().oneshot(()).await,
+            Err(BufferError::Closed)
+        ));
+        assert_eq!(metrics.queue_depth(), 0);
+        assert_eq!(metrics.dispatched(), 0);
+    }
+
+    // 调用会一直等到 `release` 被通知
+    #[derive(Clone)]
+    struct Gated {
+        release: Arc<Notify>,
+    }
+
+    impl Service<()> for Gated {
+        type Response = ();
+        type Error = Infallible;
+        type Future = futures_util::future::BoxFuture<'static, Result<(), Self::Error>>;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, _req: ()) -> Self::Future {
+            let release = self.release.clone();
+            Box::pin(async move {
+                release.notified().await;
+                Ok(())
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn load_shed_rejects_when_full() {
+        let builder = Builder::new().capacity(1).load_shed(true);
+        let metrics = builder.metrics();
+        let release = Arc::new(Notify::new());
+        let mut buffer = builder.build(Gated {
+            release: release.clone(),
+        });
+
+        let in_flight = buffer.ready().await.unwrap().call(());
+
+        let rejected = buffer.clone().oneshot(()).await;
+        assert!(matches!(rejected, Err(BufferError::Overloaded)));
+        assert_eq!(metrics.rejected(), 1);
+
+        release.notify_one();
+        in_flight.await.unwrap();
+
+        // 请求完成之后空位被释放
+        let next = tokio::spawn(buffer.clone().oneshot(()));
+        tokio::task::yield_now().await;
+        release.notify_one();
+        next.await.unwrap().unwrap();
+        assert_eq!(metrics.rejected(), 1);
+        assert_eq!(metrics.dispatched(), 2);
+    }
+
+    #[tokio::test]
+    async fn waits_for_capacity() {
+        let builder = Builder::new().capacity(1);
+        let release = Arc::new(Notify::new());
+        let mut buffer = builder.build(Gated {
+            release: release.clone(),
+        });
+
+        let in_flight = buffer.ready().await.unwrap().call(());
+
+        let mut waiting = buffer.clone();
+        let ready = tokio::time::timeout(Duration::from_millis(20), waiting.ready()).await;
+        assert!(ready.is_err(), "buffer should be full");
+
+        release.notify_one();
+        in_flight.await.unwrap();
+
+        let next = waiting.ready().await.unwrap().call(());
+        release.notify_one();
+        next.await.unwrap();
+        assert_eq!(builder.metrics().rejected(), 0);
+    }
+
+    // 第一次 `poll_ready` 要等一段时间
+    struct SlowReady {
+        delay: Option<Pin<Box<tokio::time::Sleep>>>,
+    }
+
+    impl Service<()> for SlowReady {
+        type Response = ();
+        type Error = Infallible;
+        type Future = std::future::Ready<Result<(), Self::Error>>;
+
+        fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            if let Some(delay) = &mut self.delay {
+                ready!(delay.as_mut().poll(cx));
+                self.delay = None;
+            }
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, _req: ()) -> Self::Future {
+            std::future::ready(Ok(()))
+        }
+    }
+
+    #[tokio::test]
+    async fn records_dispatched_and_wait_time() {
+        let builder = Builder::new();
+        let metrics = builder.metrics();
+        let buffer = builder.build(SlowReady {
+            delay: Some(Box::pin(tokio::time::sleep(Duration::from_millis(50)))),
+        });
+
+        buffer.clone().oneshot(()).await.unwrap();
+        assert_eq!(metrics.dispatched(), 1);
+        assert!(metrics.wait_time() >= Duration::from_millis(40));
+
+        buffer.clone().oneshot(()).await.unwrap();
+        buffer.clone().oneshot(()).await.unwrap();
+        assert_eq!(metrics.dispatched(), 3);
+        assert_eq!(metrics.queue_depth(), 0);
+        assert_eq!(metrics.rejected(), 0);
+    }
+
+    #[tokio::test]
+    async fn boxed_router_responds_503_when_overloaded() {
+        let entered = Arc::new(Notify::new());
+        let release = Arc::new(Notify::new());
+
+        let handler = {
+            let entered = entered.clone();
+            let release = release.clone();
+            move || {
+                let entered = entered.clone();
+                let release = release.clone();
+                async move {
+                    entered.notify_one();
+                    release.notified().await;
+                    "done"
+                }
+            }
+        };
+
+        let buffer = Builder::new().capacity(1).load_shed(true);
+        let app = Router::new()
+            .route("/", get(handler))
+            .boxed_with(&buffer);
+        let client = TestClient::new(app);
+
+        let in_flight = tokio::spawn(client.get("/").send());
+        entered.notified().await;
+
+        client
+            .get("/")
+            .send()
+            .await
+            .assert_status(StatusCode::SERVICE_UNAVAILABLE)
+            .assert_text("");
+        assert_eq!(buffer.metrics().rejected(), 1);
+
+        release.notify_one();
+        in_flight
+            .await
+            .unwrap()
+            .assert_status(StatusCode::OK)
+            .assert_text("done");
+    }
+}
@@ -10,9 +10,18 @@ pub mod builtin;
 
 pub use self::{
     request_parts::{Body, BodyStream},
-    builtin::{TypedHeader, Query},
+    builtin::{TypedHeader, Query, Path, Form, Extension, ContentLengthLimit, LimitedBody},
 
 };
+pub use crate::json::Json;
+
+#[cfg(feature = "multipart")]
+#[cfg_attr(docsrs, doc(cfg(feature = "multipart")))]
+pub use self::builtin::{Field, Multipart, MultipartError, MultipartLimits};
+
+#[cfg(feature = "ws")]
+#[cfg_attr(docsrs, doc(cfg(feature = "ws")))]
+pub use self::builtin::{ws, WebSocketUpgrade};
 
 #[async_trait]
 pub trait FromRequest<B = crate::body::Body>: Sized {
@@ -255,7 +264,22 @@ pub(crate) fn has_content_type<B>(
         return Ok(false);
     };
 
-    Ok(content_type.starts_with(expected_content_type))
+    // 忽略 `; charset=utf-8` 之类的参数
+    let essence = content_type.split(';').next().unwrap_or_default().trim();
+    let (type_, subtype) = match essence.split_once('/') {
+        Some(parts) => parts,
+        None => return Ok(false),
+    };
+    let (expected_type, expected_subtype) = expected_content_type
+        .split_once('/')
+        .expect("expected content type must be `type/subtype`");
+
+    // `application/problem+json` 这类带后缀的类型也算 `application/json`
+    let subtype = subtype.to_ascii_lowercase();
+    let suffix = subtype.rsplit_once('+').map(|(_, suffix)| suffix);
+
+    Ok(type_.eq_ignore_ascii_case(expected_type)
+        && (subtype == expected_subtype || suffix == Some(expected_subtype)))
 }
 
 pub(crate) fn take_body<B>(req: &mut RequestParts<B>) -> Result<B, BodyAlreadyExtracted> {
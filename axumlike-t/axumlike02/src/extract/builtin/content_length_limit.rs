@@ -0,0 +1,310 @@
+use super::{
+    ContentLengthLimitRejection, FromRequest, HeadersAlreadyExtracted, LengthRequired,
+    PayloadTooLarge, RequestParts,
+};
+use crate::BoxError;
+use async_trait::async_trait;
+use bytes::Buf;
+use futures_util::ready;
+use http::{header, HeaderMap};
+use pin_project_lite::pin_project;
+use std::{
+    fmt,
+    ops::{Deref, DerefMut},
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+};
+
+/// Extractor that will reject requests with a body larger than some size.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use axumlike02::{
+///     extract::ContentLengthLimit,
+///     handler::on,
+///     router::MethodFilter,
+///     Router,
+/// };
+///
+/// async fn handler(body: ContentLengthLimit<String, 1024>) {
+///     // ...
+/// }
+///
+/// let app = Router::new().route("/", on(MethodFilter::POST, handler));
+/// # async {
+/// # axumlike02::Server::bind(&"".parse().unwrap()).serve(app.into_make_service()).await.unwrap();
+/// # };
+/// ```
+///
+/// Requests whose `Content-Length`, or otherwise known size, is larger than `N`
+/// bytes are rejected with `413 Payload Too Large` without reading the body.
+/// Requests with an invalid `Content-Length` are rejected with
+/// `411 Length Required`.
+///
+/// `T` is then extracted from the request with the body wrapped in a
+/// [`LimitedBody`], which counts the bytes as they are read. This way chunked
+/// requests are accepted too. If `T` fails because the body went over `N`
+/// bytes the request is rejected with `413 Payload Too Large`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ContentLengthLimit<T, const N: u64>(pub T);
+
+#[async_trait]
+impl<T, B, const N: u64> FromRequest<B> for ContentLengthLimit<T, N>
+where
+    T: FromRequest<LimitedBody<B>>,
+    B: http_body::Body + Send,
+    B::Error: Into<BoxError>,
+{
+    type Rejection = ContentLengthLimitRejection<T::Rejection>;
+
+    async fn from_request(req: &mut RequestParts<B>) -> Result<Self, Self::Rejection> {
+        let content_length = req
+            .headers()
+            .ok_or(ContentLengthLimitRejection::HeadersAlreadyExtracted(
+                HeadersAlreadyExtracted,
+            ))?
+            .get(header::CONTENT_LENGTH);
+
+        match content_length {
+            Some(content_length) => {
+                let content_length = content_length
+                    .to_str()
+                    .ok()
+                    .and_then(|content_length| content_length.parse::<u64>().ok())
+                    .ok_or(ContentLengthLimitRejection::LengthRequired(LengthRequired))?;
+
+                if content_length > N {
+                    return Err(ContentLengthLimitRejection::PayloadTooLarge(
+                        PayloadTooLarge,
+                    ));
+                }
+            }
+            None => {
+                // 没有长度时先看请求体自己给出的上限，不知道的话边读边数
+                let upper = req.body.as_ref().and_then(|body| body.size_hint().upper());
+
+                if matches!(upper, Some(upper) if upper > N) {
+                    return Err(ContentLengthLimitRejection::PayloadTooLarge(
+                        PayloadTooLarge,
+                    ));
+                }
+            }
+        }
+
+        let exceeded = Arc::new(AtomicBool::new(false));
+        let mut limited = RequestParts {
+            method: req.method.clone(),
+            uri: req.uri.clone(),
+            version: req.version,
+            headers: req.headers.take(),
+            extensions: req.extensions.take(),
+            body: req.body.take().map(|body| LimitedBody {
+                inner: body,
+                remaining: N,
+                exceeded: exceeded.clone(),
+            }),
+        };
+
+        let result = T::from_request(&mut limited).await;
+
+        // `T` 没有取走的部分放回原来的请求
+        req.method = limited.method;
+        req.uri = limited.uri;
+        req.version = limited.version;
+        req.headers = limited.headers;
+        req.extensions = limited.extensions;
+        req.body = limited.body.map(|body| body.inner);
+
+        match result {
+            Ok(value) => Ok(Self(value)),
+            Err(_) if exceeded.load(Ordering::SeqCst) => Err(
+                ContentLengthLimitRejection::PayloadTooLarge(PayloadTooLarge),
+            ),
+            Err(rejection) => Err(ContentLengthLimitRejection::Inner(rejection)),
+        }
+    }
+}
+
+pin_project! {
+    /// Request body handed to the extractor wrapped by [`ContentLengthLimit`].
+    ///
+    /// Yields an error instead of the chunk that takes the body over the limit.
+    #[derive(Debug)]
+    pub struct LimitedBody<B> {
+        #[pin]
+        inner: B,
+        remaining: u64,
+        exceeded: Arc<AtomicBool>,
+    }
+}
+
+impl<B> http_body::Body for LimitedBody<B>
+where
+    B: http_body::Body,
+    B::Error: Into<BoxError>,
+{
+    type Data = B::Data;
+    type Error = BoxError;
+
+    fn poll_data(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        let this = self.project();
+
+        match ready!(this.inner.poll_data(cx)) {
+            Some(Ok(data)) => {
+                let len = data.remaining() as u64;
+
+                if len > *this.remaining {
+                    this.exceeded.store(true, Ordering::SeqCst);
+                    return Poll::Ready(Some(Err(Box::new(LengthLimitExceeded))));
+                }
+
+                *this.remaining -= len;
+                Poll::Ready(Some(Ok(data)))
+            }
+            Some(Err(err)) => Poll::Ready(Some(Err(err.into()))),
+            None => Poll::Ready(None),
+        }
+    }
+
+    fn poll_trailers(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<HeaderMap>, Self::Error>> {
+        self.project().inner.poll_trailers(cx).map_err(Into::into)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> http_body::SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+#[derive(Debug)]
+struct LengthLimitExceeded;
+
+impl fmt::Display for LengthLimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Request body is larger than the length limit")
+    }
+}
+
+impl std::error::Error for LengthLimitExceeded {}
+
+impl<T, const N: u64> Deref for ContentLengthLimit<T, N> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T, const N: u64> DerefMut for ContentLengthLimit<T, N> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        body::Body, handler::on, http::StatusCode, router::MethodFilter,
+        test_client::TestClient, Router,
+    };
+    use bytes::Bytes;
+    use std::convert::Infallible;
+
+    async fn echo(ContentLengthLimit(body): ContentLengthLimit<String, 8>) -> String {
+        body
+    }
+
+    #[tokio::test]
+    async fn checks_content_length() {
+        let client = TestClient::new(Router::new().route("/", on(MethodFilter::POST, echo)));
+
+        client
+            .post("/")
+            .header("content-length", "5")
+            .body("hello")
+            .send()
+            .await
+            .assert_status(StatusCode::OK)
+            .assert_text("hello");
+
+        client
+            .post("/")
+            .header("content-length", "12")
+            .body("hello world!")
+            .send()
+            .await
+            .assert_status(StatusCode::PAYLOAD_TOO_LARGE);
+
+        client
+            .post("/")
+            .header("content-length", "five")
+            .body("hello")
+            .send()
+            .await
+            .assert_status(StatusCode::LENGTH_REQUIRED);
+    }
+
+    #[tokio::test]
+    async fn without_content_length() {
+        let client = TestClient::new(Router::new().route("/", on(MethodFilter::POST, echo)));
+
+        // 请求体的大小已知时不需要 `Content-Length`
+        client
+            .post("/")
+            .body("hello")
+            .send()
+            .await
+            .assert_status(StatusCode::OK)
+            .assert_text("hello");
+
+        client
+            .post("/")
+            .body("hello world!")
+            .send()
+            .await
+            .assert_status(StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[tokio::test]
+    async fn counts_chunked_body() {
+        let client = TestClient::new(Router::new().route("/", on(MethodFilter::POST, echo)));
+
+        let chunks = futures_util::stream::iter(vec![
+            Ok::<_, Infallible>(Bytes::from("hel")),
+            Ok(Bytes::from("lo")),
+        ]);
+        client
+            .post("/")
+            .body(Body::wrap_stream(chunks))
+            .send()
+            .await
+            .assert_status(StatusCode::OK)
+            .assert_text("hello");
+
+        let chunks = futures_util::stream::iter(vec![
+            Ok::<_, Infallible>(Bytes::from("hello ")),
+            Ok(Bytes::from("world!")),
+        ]);
+        client
+            .post("/")
+            .body(Body::wrap_stream(chunks))
+            .send()
+            .await
+            .assert_status(StatusCode::PAYLOAD_TOO_LARGE);
+    }
+}
@@ -0,0 +1,146 @@
+use super::{
+    ExtensionRejection, ExtensionsAlreadyExtracted, FromRequest, MissingExtension, RequestParts,
+};
+use async_trait::async_trait;
+use std::ops::{Deref, DerefMut};
+
+/// Extractor that gets a value from request extensions.
+///
+/// This is commonly used to share state across handlers, such as database
+/// pools or configuration. The value is added to every request with
+/// [`AddExtensionLayer`](crate::AddExtensionLayer) and cloned for each
+/// handler that extracts it, so it's usually wrapped in an `Arc`.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use axumlike02::{
+///     extract::Extension,
+///     handler::get,
+///     AddExtensionLayer,
+///     Router,
+/// };
+/// use std::sync::Arc;
+///
+/// // Some shared state used throughout our application
+/// struct State {
+///     // ...
+/// }
+///
+/// async fn handler(state: Extension<Arc<State>>) {
+///     // ...
+/// }
+///
+/// let state = Arc::new(State { /* ... */ });
+///
+/// let app = Router::new().route("/", get(handler))
+///     // Add middleware that inserts the state into all incoming request's
+///     // extensions.
+///     .layer(AddExtensionLayer::new(state));
+/// # async {
+/// # axumlike02::Server::bind(&"".parse().unwrap()).serve(app.into_make_service()).await.unwrap();
+/// # };
+/// ```
+///
+/// If the extension is missing the request is rejected with a
+/// `500 Internal Server Error` response naming the missing type.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Extension<T>(pub T);
+
+#[async_trait]
+impl<T, B> FromRequest<B> for Extension<T>
+where
+    T: Clone + Send + Sync + 'static,
+    B: Send,
+{
+    type Rejection = ExtensionRejection;
+
+    async fn from_request(req: &mut RequestParts<B>) -> Result<Self, Self::Rejection> {
+        let value = req
+            .extensions()
+            .ok_or(ExtensionsAlreadyExtracted)?
+            .get::<T>()
+            .ok_or_else(|| {
+                MissingExtension::from_err(format!(
+                    "Extension of type `{}` was not found. Perhaps you forgot to add it?",
+                    std::any::type_name::<T>()
+                ))
+            })
+            .cloned()?;
+
+        Ok(Extension(value))
+    }
+}
+
+impl<T> Deref for Extension<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for Extension<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        handler::get, http::StatusCode, test_client::TestClient, AddExtensionLayer, Router,
+    };
+    use std::sync::Arc;
+
+    #[derive(Clone)]
+    struct State {
+        name: &'static str,
+    }
+
+    async fn handler(Extension(state): Extension<Arc<State>>) -> &'static str {
+        state.name
+    }
+
+    #[tokio::test]
+    async fn extracts_extension() {
+        let app = Router::new()
+            .route("/", get(handler))
+            .layer(AddExtensionLayer::new(Arc::new(State { name: "axum" })));
+        let client = TestClient::new(app);
+
+        client
+            .get("/")
+            .send()
+            .await
+            .assert_status(StatusCode::OK)
+            .assert_text("axum");
+    }
+
+    #[tokio::test]
+    async fn missing_extension() {
+        let client = TestClient::new(Router::new().route("/", get(handler)));
+
+        client
+            .get("/")
+            .send()
+            .await
+            .assert_status(StatusCode::INTERNAL_SERVER_ERROR)
+            .assert_text(&format!(
+                "Missing request extension: Extension of type `{}` was not found. \
+                 Perhaps you forgot to add it?",
+                std::any::type_name::<Arc<State>>()
+            ));
+
+        // 类型必须完全一致，`State` 不能当作 `Arc<State>`
+        let app = Router::new()
+            .route("/", get(handler))
+            .layer(AddExtensionLayer::new(State { name: "axum" }));
+        TestClient::new(app)
+            .get("/")
+            .send()
+            .await
+            .assert_status(StatusCode::INTERNAL_SERVER_ERROR);
+    }
+}
@@ -0,0 +1,252 @@
+use super::{
+    FailedToBufferBody, FailedToDeserializeQueryString, FormRejection, FromRequest,
+    InvalidFormContentType, RequestParts,
+};
+use crate::{
+    extract::{has_content_type, take_body},
+    response::IntoResponse,
+    BoxError,
+};
+use async_trait::async_trait;
+use bytes::Bytes;
+use http::{header, HeaderValue, Method, Response, StatusCode};
+use http_body::Full;
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    convert::Infallible,
+    ops::{Deref, DerefMut},
+};
+
+/// Extractor that deserializes `application/x-www-form-urlencoded` requests
+/// into some type.
+///
+/// `T` is expected to implement [`serde::Deserialize`].
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use axumlike02::{
+///     extract::Form,
+///     handler::on,
+///     router::MethodFilter,
+///     Router,
+/// };
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct SignUp {
+///     username: String,
+///     password: String,
+/// }
+///
+/// async fn accept_form(form: Form<SignUp>) {
+///     let sign_up: SignUp = form.0;
+///
+///     // ...
+/// }
+///
+/// let app = Router::new().route("/sign_up", on(MethodFilter::POST, accept_form));
+/// # async {
+/// # axumlike02::Server::bind(&"".parse().unwrap()).serve(app.into_make_service()).await.unwrap();
+/// # };
+/// ```
+///
+/// For `GET` and `HEAD` requests the form is read from the query string,
+/// like [`Query`](super::Query). For other methods it's read from the body,
+/// and the request is rejected with `400 Bad Request` unless it has
+/// `Content-Type: application/x-www-form-urlencoded`.
+///
+/// `Form` can also be used as a response, which serializes `T` and sets the
+/// `Content-Type` to `application/x-www-form-urlencoded`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Form<T>(pub T);
+
+#[async_trait]
+impl<T, B> FromRequest<B> for Form<T>
+where
+    T: DeserializeOwned,
+    B: http_body::Body + Send,
+    B::Data: Send,
+    B::Error: Into<BoxError>,
+{
+    type Rejection = FormRejection;
+
+    async fn from_request(req: &mut RequestParts<B>) -> Result<Self, Self::Rejection> {
+        if req.method() == Method::GET || req.method() == Method::HEAD {
+            let query = req.uri().query().unwrap_or_default();
+            let value = serde_urlencoded::from_str(query)
+                .map_err(FailedToDeserializeQueryString::new::<T, _>)?;
+            return Ok(Form(value));
+        }
+
+        if !has_content_type(req, "application/x-www-form-urlencoded")? {
+            return Err(InvalidFormContentType.into());
+        }
+
+        let body = take_body(req)?;
+        let bytes = hyper::body::to_bytes(body)
+            .await
+            .map_err(FailedToBufferBody::from_err)?;
+
+        let value = serde_urlencoded::from_bytes(&bytes)
+            .map_err(FailedToDeserializeQueryString::new::<T, _>)?;
+
+        Ok(Form(value))
+    }
+}
+
+impl<T> Deref for Form<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for Form<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl<T> IntoResponse for Form<T>
+where
+    T: Serialize,
+{
+    type Body = Full<Bytes>;
+    type BodyError = Infallible;
+
+    fn into_response(self) -> Response<Self::Body> {
+        let body = match serde_urlencoded::to_string(&self.0) {
+            Ok(body) => body,
+            Err(err) => {
+                let mut res = Response::new(Full::from(err.to_string()));
+                *res.status_mut() = StatusCode::INTERNAL_SERVER_ERROR;
+                res.headers_mut()
+                    .insert(header::CONTENT_TYPE, HeaderValue::from_static("text/plain"));
+                return res;
+            }
+        };
+
+        let mut res = Response::new(Full::from(body));
+        res.headers_mut().insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("application/x-www-form-urlencoded"),
+        );
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        handler::on, http::StatusCode, router::MethodFilter, test_client::TestClient, Router,
+    };
+    use serde::Deserialize;
+
+    #[derive(Debug, Deserialize, Serialize)]
+    struct Pagination {
+        page: u32,
+        per_page: Option<u32>,
+    }
+
+    async fn echo(Form(pagination): Form<Pagination>) -> Form<Pagination> {
+        Form(pagination)
+    }
+
+    #[tokio::test]
+    async fn get_and_head_read_the_query_string() {
+        let client = TestClient::new(Router::new().route("/", on(MethodFilter::all(), echo)));
+
+        client
+            .get("/?page=2&per_page=10")
+            .send()
+            .await
+            .assert_status(StatusCode::OK)
+            .assert_header("content-type", "application/x-www-form-urlencoded")
+            .assert_text("page=2&per_page=10");
+
+        // 即使带了表单的 `Content-Type`，也不会读取请求体
+        client
+            .get("/?page=3")
+            .header("content-type", "application/x-www-form-urlencoded")
+            .body("page=4")
+            .send()
+            .await
+            .assert_status(StatusCode::OK)
+            .assert_text("page=3");
+
+        client
+            .head("/?page=2")
+            .send()
+            .await
+            .assert_status(StatusCode::OK);
+
+        client
+            .head("/?page=two")
+            .send()
+            .await
+            .assert_status(StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn post_reads_the_body() {
+        let client = TestClient::new(Router::new().route("/", on(MethodFilter::all(), echo)));
+
+        client
+            .post("/?page=1")
+            .header("content-type", "application/x-www-form-urlencoded")
+            .body("page=2&per_page=10")
+            .send()
+            .await
+            .assert_status(StatusCode::OK)
+            .assert_text("page=2&per_page=10");
+
+        // 参数会被忽略
+        client
+            .post("/")
+            .header(
+                "content-type",
+                "application/x-www-form-urlencoded; charset=utf-8",
+            )
+            .body("page=5")
+            .send()
+            .await
+            .assert_status(StatusCode::OK)
+            .assert_text("page=5");
+
+        let res = client
+            .post("/")
+            .header("content-type", "application/x-www-form-urlencoded")
+            .body("page=five")
+            .send()
+            .await;
+        res.assert_status(StatusCode::BAD_REQUEST);
+        assert!(res.text().starts_with("Failed to deserialize query string"));
+    }
+
+    #[tokio::test]
+    async fn rejects_other_content_types() {
+        let client = TestClient::new(Router::new().route("/", on(MethodFilter::all(), echo)));
+
+        for content_type in &["application/json", "text/plain"] {
+            client
+                .post("/")
+                .header("content-type", *content_type)
+                .body("page=2")
+                .send()
+                .await
+                .assert_status(StatusCode::BAD_REQUEST)
+                .assert_text("Form requests must have `Content-Type: x-www-form-urlencoded`");
+        }
+
+        client
+            .post("/")
+            .body("page=2")
+            .send()
+            .await
+            .assert_status(StatusCode::BAD_REQUEST)
+            .assert_text("Form requests must have `Content-Type: x-www-form-urlencoded`");
+    }
+}
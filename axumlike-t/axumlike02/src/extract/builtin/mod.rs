@@ -1,12 +1,27 @@
 pub mod typed_header;
 pub mod query;
+pub mod path;
+pub mod form;
+pub mod extension;
+pub mod content_length_limit;
+#[cfg(feature = "multipart")]
+#[cfg_attr(docsrs, doc(cfg(feature = "multipart")))]
+pub mod multipart;
+#[cfg(feature = "ws")]
+#[cfg_attr(docsrs, doc(cfg(feature = "ws")))]
+pub mod ws;
 
-use crate::{response::IntoResponse, error::Error};
-use async_trait::async_trait;
-use http::{header, Extensions, HeaderMap, Method, Request, Uri, Version};
+// 子模块通过 `super::` 引用提取器 trait 和拒绝类型
 use super::rejection::*;
-use std::convert::Infallible;
 use crate::extract::{FromRequest, RequestParts};
 
 pub use self::typed_header::TypedHeader;
-pub use self::query::Query;
\ No newline at end of file
+pub use self::query::Query;
+pub use self::path::Path;
+pub use self::form::Form;
+pub use self::extension::Extension;
+pub use self::content_length_limit::{ContentLengthLimit, LimitedBody};
+#[cfg(feature = "multipart")]
+pub use self::multipart::{Field, Multipart, MultipartError, MultipartLimits};
+#[cfg(feature = "ws")]
+pub use self::ws::WebSocketUpgrade;
\ No newline at end of file
@@ -0,0 +1,417 @@
+use super::{BodyAlreadyExtracted, FromRequest, HeadersAlreadyExtracted, RequestParts};
+use crate::{extract::BodyStream, response::IntoResponse, BoxError};
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures_util::stream::Stream;
+use http::{header, HeaderMap, Response, StatusCode};
+use http_body::Full;
+use std::{
+    convert::Infallible,
+    fmt,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+/// Extractor that parses `multipart/form-data` requests, commonly used with
+/// file uploads.
+///
+/// Fields are read one at a time as the body arrives, so large files don't
+/// have to be buffered in memory.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use axumlike02::{
+///     extract::Multipart,
+///     handler::on,
+///     router::MethodFilter,
+///     Router,
+/// };
+///
+/// async fn upload(mut multipart: Multipart) {
+///     while let Some(field) = multipart.next_field().await.unwrap() {
+///         let name = field.name().unwrap_or_default().to_owned();
+///         let data = field.bytes().await.unwrap();
+///
+///         println!("Length of `{}` is {} bytes", name, data.len());
+///     }
+/// }
+///
+/// let app = Router::new().route("/upload", on(MethodFilter::POST, upload));
+/// # async {
+/// # axumlike02::Server::bind(&"".parse().unwrap()).serve(app.into_make_service()).await.unwrap();
+/// # };
+/// ```
+///
+/// The request is rejected with `400 Bad Request` if it doesn't have a
+/// `Content-Type` of `multipart/form-data` with a `boundary`.
+///
+/// # Size limits
+///
+/// By default the whole body may be at most 2 MiB and fields have no limit of
+/// their own. The limits can be changed by adding [`MultipartLimits`] to the
+/// request extensions:
+///
+/// ```rust,no_run
+/// use axumlike02::{
+///     extract::{Multipart, MultipartLimits},
+///     handler::on,
+///     router::MethodFilter,
+///     AddExtensionLayer,
+///     Router,
+/// };
+///
+/// async fn upload(mut multipart: Multipart) {
+///     // ...
+/// }
+///
+/// let app = Router::new()
+///     .route("/upload", on(MethodFilter::POST, upload))
+///     .layer(AddExtensionLayer::new(
+///         MultipartLimits::new()
+///             .field(10 * 1024 * 1024)
+///             .total(50 * 1024 * 1024),
+///     ));
+/// # async {
+/// # axumlike02::Server::bind(&"".parse().unwrap()).serve(app.into_make_service()).await.unwrap();
+/// # };
+/// ```
+///
+/// Reading past a limit fails with a [`MultipartError`] which responds with
+/// `413 Payload Too Large`.
+#[derive(Debug)]
+pub struct Multipart {
+    inner: multer::Multipart<'static>,
+}
+
+#[async_trait]
+impl<B> FromRequest<B> for Multipart
+where
+    B: http_body::Body<Data = Bytes> + Unpin + Send + 'static,
+    B::Error: Into<BoxError> + 'static,
+{
+    type Rejection = MultipartRejection;
+
+    async fn from_request(req: &mut RequestParts<B>) -> Result<Self, Self::Rejection> {
+        let boundary = parse_boundary(req.headers().ok_or(HeadersAlreadyExtracted)?)
+            .ok_or(InvalidBoundary)?;
+
+        // 没有配置或扩展已被取走时使用默认限制
+        let limits = req
+            .extensions()
+            .and_then(|extensions| extensions.get::<MultipartLimits>())
+            .copied()
+            .unwrap_or_default();
+
+        let stream = BodyStream::from_request(req).await?;
+        let inner = multer::Multipart::with_constraints(stream, boundary, limits.constraints());
+
+        Ok(Self { inner })
+    }
+}
+
+impl Multipart {
+    /// Yields the next [`Field`] if available.
+    ///
+    /// Fields borrow the `Multipart` so only one can be read at a time, the
+    /// rest of a field that isn't read completely is skipped.
+    pub async fn next_field(&mut self) -> Result<Option<Field<'_>>, MultipartError> {
+        let field = self
+            .inner
+            .next_field()
+            .await
+            .map_err(MultipartError::from_multer)?;
+
+        Ok(field.map(move |inner| Field {
+            inner,
+            _multipart: self,
+        }))
+    }
+}
+
+/// A single field in a multipart stream.
+///
+/// The data of the field is a [`Stream`] of chunks, or can be read all at once
+/// with [`Field::bytes`] or [`Field::text`].
+#[derive(Debug)]
+pub struct Field<'a> {
+    inner: multer::Field<'static>,
+    // 保证同一时间只有一个字段被读取
+    _multipart: &'a mut Multipart,
+}
+
+impl<'a> Stream for Field<'a> {
+    type Item = Result<Bytes, MultipartError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.inner)
+            .poll_next(cx)
+            .map_err(MultipartError::from_multer)
+    }
+}
+
+impl<'a> Field<'a> {
+    /// The field name found in the `Content-Disposition` header.
+    pub fn name(&self) -> Option<&str> {
+        self.inner.name()
+    }
+
+    /// The file name found in the `Content-Disposition` header, if the field
+    /// is a file.
+    pub fn file_name(&self) -> Option<&str> {
+        self.inner.file_name()
+    }
+
+    /// The `Content-Type` of the field.
+    pub fn content_type(&self) -> Option<&mime::Mime> {
+        self.inner.content_type()
+    }
+
+    /// All the headers of the field.
+    pub fn headers(&self) -> &HeaderMap {
+        self.inner.headers()
+    }
+
+    /// Get the next chunk of the field's data, `None` once it has been read
+    /// completely.
+    pub async fn chunk(&mut self) -> Result<Option<Bytes>, MultipartError> {
+        self.inner
+            .chunk()
+            .await
+            .map_err(MultipartError::from_multer)
+    }
+
+    /// Read the whole data of the field.
+    pub async fn bytes(self) -> Result<Bytes, MultipartError> {
+        self.inner
+            .bytes()
+            .await
+            .map_err(MultipartError::from_multer)
+    }
+
+    /// Read the whole data of the field as text.
+    ///
+    /// The charset from the field's `Content-Type` is used if there is one,
+    /// otherwise the data is decoded as UTF-8.
+    pub async fn text(self) -> Result<String, MultipartError> {
+        self.inner
+            .text()
+            .await
+            .map_err(MultipartError::from_multer)
+    }
+}
+
+/// Size limits for [`Multipart`], read from the request extensions.
+///
+/// See [`Multipart`] for an example.
+#[derive(Debug, Clone, Copy)]
+pub struct MultipartLimits {
+    field: Option<u64>,
+    total: u64,
+}
+
+impl MultipartLimits {
+    /// Create the default limits, 2 MiB for the whole body and no limit for
+    /// single fields.
+    pub fn new() -> Self {
+        Self {
+            field: None,
+            total: 2 * 1024 * 1024,
+        }
+    }
+
+    /// Limit the size of the data of each field, in bytes.
+    pub fn field(mut self, limit: u64) -> Self {
+        self.field = Some(limit);
+        self
+    }
+
+    /// Limit the size of the whole body, in bytes.
+    pub fn total(mut self, limit: u64) -> Self {
+        self.total = limit;
+        self
+    }
+
+    fn constraints(&self) -> multer::Constraints {
+        let mut size_limit = multer::SizeLimit::new().whole_stream(self.total);
+        if let Some(field) = self.field {
+            size_limit = size_limit.per_field(field);
+        }
+
+        multer::Constraints::new().size_limit(size_limit)
+    }
+}
+
+impl Default for MultipartLimits {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Errors associated with parsing `multipart/form-data` requests.
+///
+/// Responds with `413 Payload Too Large` if a [`MultipartLimits`] was
+/// exceeded and `400 Bad Request` otherwise, so handlers can return it
+/// directly.
+#[derive(Debug)]
+pub struct MultipartError {
+    source: multer::Error,
+}
+
+impl MultipartError {
+    fn from_multer(source: multer::Error) -> Self {
+        Self { source }
+    }
+
+    fn status(&self) -> StatusCode {
+        if is_size_exceeded(&self.source) {
+            StatusCode::PAYLOAD_TOO_LARGE
+        } else {
+            StatusCode::BAD_REQUEST
+        }
+    }
+}
+
+fn is_size_exceeded(err: &multer::Error) -> bool {
+    match err {
+        multer::Error::FieldSizeExceeded { .. } | multer::Error::StreamSizeExceeded { .. } => true,
+        // 超出整体限制时 multer 会把错误包在读取失败里
+        multer::Error::StreamReadFailed(err) => err
+            .downcast_ref::<multer::Error>()
+            .is_some_and(is_size_exceeded),
+        _ => false,
+    }
+}
+
+impl IntoResponse for MultipartError {
+    type Body = Full<Bytes>;
+    type BodyError = Infallible;
+
+    fn into_response(self) -> Response<Self::Body> {
+        let mut res = Response::new(Full::from(format!("{}: {}", self, self.source)));
+        *res.status_mut() = self.status();
+        res
+    }
+}
+
+impl fmt::Display for MultipartError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Error parsing `multipart/form-data` request")
+    }
+}
+
+impl std::error::Error for MultipartError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+fn parse_boundary(headers: &HeaderMap) -> Option<String> {
+    let content_type = headers.get(header::CONTENT_TYPE)?.to_str().ok()?;
+    multer::parse_boundary(content_type).ok()
+}
+
+define_rejection! {
+    #[status = BAD_REQUEST]
+    #[body = "Invalid `boundary` for `multipart/form-data` request"]
+    /// Rejection type used if the `boundary` in a `multipart/form-data` is
+    /// missing or invalid.
+    pub struct InvalidBoundary;
+}
+
+composite_rejection! {
+    /// Rejection used for [`Multipart`].
+    ///
+    /// Contains one variant for each way the [`Multipart`] extractor can fail.
+    pub enum MultipartRejection {
+        BodyAlreadyExtracted,
+        HeadersAlreadyExtracted,
+        InvalidBoundary,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        handler::on,
+        router::MethodFilter,
+        test_client::{TestClient, TestResponse},
+        AddExtensionLayer, Router,
+    };
+
+    const BODY: &str = "--XX\r\n\
+        Content-Disposition: form-data; name=\"greeting\"\r\n\r\n\
+        hello\r\n\
+        --XX\r\n\
+        Content-Disposition: form-data; name=\"file\"; filename=\"digits.txt\"\r\n\
+        Content-Type: text/plain\r\n\r\n\
+        0123456789\r\n\
+        --XX--\r\n";
+
+    async fn upload(mut multipart: Multipart) -> Result<String, MultipartError> {
+        let mut fields = Vec::new();
+        while let Some(field) = multipart.next_field().await? {
+            let name = field.name().unwrap_or_default().to_owned();
+            let file_name = field.file_name().map(str::to_owned);
+            let data = field.bytes().await?;
+            fields.push(format!("{} {:?} {}", name, file_name, data.len()));
+        }
+        Ok(fields.join(", "))
+    }
+
+    async fn send(limits: MultipartLimits, content_type: &str) -> TestResponse {
+        let app = Router::new()
+            .route("/", on(MethodFilter::POST, upload))
+            .layer(AddExtensionLayer::new(limits));
+
+        TestClient::new(app)
+            .post("/")
+            .header("content-type", content_type)
+            .body(BODY)
+            .send()
+            .await
+    }
+
+    #[tokio::test]
+    async fn reads_fields() {
+        send(MultipartLimits::new(), "multipart/form-data; boundary=XX")
+            .await
+            .assert_status(StatusCode::OK)
+            .assert_text("greeting None 5, file Some(\"digits.txt\") 10");
+    }
+
+    #[tokio::test]
+    async fn missing_boundary() {
+        send(MultipartLimits::new(), "multipart/form-data")
+            .await
+            .assert_status(StatusCode::BAD_REQUEST);
+        send(MultipartLimits::new(), "text/plain")
+            .await
+            .assert_status(StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn field_limit() {
+        send(MultipartLimits::new().field(10), "multipart/form-data; boundary=XX")
+            .await
+            .assert_status(StatusCode::OK);
+        send(MultipartLimits::new().field(8), "multipart/form-data; boundary=XX")
+            .await
+            .assert_status(StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[tokio::test]
+    async fn total_limit() {
+        send(MultipartLimits::new().total(50), "multipart/form-data; boundary=XX")
+            .await
+            .assert_status(StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[tokio::test]
+    async fn malformed_body() {
+        send(MultipartLimits::new(), "multipart/form-data; boundary=YY")
+            .await
+            .assert_status(StatusCode::BAD_REQUEST);
+    }
+}
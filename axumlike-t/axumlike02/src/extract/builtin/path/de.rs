@@ -0,0 +1,591 @@
+// 把路由参数反序列化成任意实现了 `Deserialize` 的类型
+//
+// 顶层的 PathDeserializer 面对的是所有参数：单个值、元组、序列或者结构体；
+// ValueDeserializer 只处理其中一个参数的值。
+use serde::{
+    de::{
+        self, value::BorrowedStrDeserializer, DeserializeSeed, EnumAccess, Error as _, MapAccess,
+        SeqAccess, VariantAccess, Visitor,
+    },
+    forward_to_deserialize_any, Deserializer,
+};
+use std::fmt;
+
+/// Error returned when the params can't be deserialized into the target type.
+#[derive(Debug)]
+pub(crate) struct PathDeserializationError(String);
+
+impl de::Error for PathDeserializationError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Self(msg.to_string())
+    }
+}
+
+impl fmt::Display for PathDeserializationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl std::error::Error for PathDeserializationError {}
+
+// 只有一个参数时，交给这个参数的 ValueDeserializer 处理
+macro_rules! single_value {
+    ($($trait_fn:ident)*) => {
+        $(
+            fn $trait_fn<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+            where
+                V: Visitor<'de>,
+            {
+                let (key, value) = self.single()?;
+                ValueDeserializer {
+                    key: Some(key),
+                    value,
+                }
+                .$trait_fn(visitor)
+            }
+        )*
+    };
+}
+
+macro_rules! parse_value {
+    ($trait_fn:ident, $visit_fn:ident, $ty:literal) => {
+        fn $trait_fn<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+        where
+            V: Visitor<'de>,
+        {
+            let value = self.value.parse().map_err(|_| {
+                let mut message = format!("Cannot parse `{}` to a `{}`", self.value, $ty);
+                if let Some(key) = self.key {
+                    message.push_str(&format!(" for `{}`", key));
+                }
+                PathDeserializationError(message)
+            })?;
+            visitor.$visit_fn(value)
+        }
+    };
+}
+
+pub(crate) struct PathDeserializer<'de> {
+    params: &'de [(String, String)],
+}
+
+impl<'de> PathDeserializer<'de> {
+    pub(crate) fn new(params: &'de [(String, String)]) -> Self {
+        Self { params }
+    }
+
+    fn single(&self) -> Result<(&'de str, &'de str), PathDeserializationError> {
+        match self.params {
+            [(key, value)] => Ok((key, value)),
+            _ => Err(PathDeserializationError::custom(format!(
+                "Wrong number of params. Expected 1 but got {}",
+                self.params.len()
+            ))),
+        }
+    }
+
+    fn expect_len(&self, len: usize) -> Result<(), PathDeserializationError> {
+        if self.params.len() == len {
+            Ok(())
+        } else {
+            Err(PathDeserializationError::custom(format!(
+                "Wrong number of params. Expected {} but got {}",
+                len,
+                self.params.len()
+            )))
+        }
+    }
+}
+
+impl<'de> Deserializer<'de> for PathDeserializer<'de> {
+    type Error = PathDeserializationError;
+
+    // 只有一个参数时当作单个值，否则当作 map
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self.params {
+            [(_, value)] => visitor.visit_borrowed_str(value),
+            _ => self.deserialize_map(visitor),
+        }
+    }
+
+    single_value! {
+        deserialize_bool deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64
+        deserialize_i128 deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64
+        deserialize_u128 deserialize_f32 deserialize_f64 deserialize_char deserialize_str
+        deserialize_string deserialize_bytes deserialize_byte_buf deserialize_identifier
+    }
+
+    // 省略了可选参数时没有值
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        if self.params.is_empty() {
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_unit<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_seq(SeqDeserializer {
+            params: self.params,
+        })
+    }
+
+    fn deserialize_tuple<V>(self, len: usize, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.expect_len(len)?;
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V>(
+        self,
+        _name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_tuple(len, visitor)
+    }
+
+    fn deserialize_map<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_map(MapDeserializer {
+            params: self.params,
+            value: None,
+        })
+    }
+
+    fn deserialize_struct<V>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        let (_, value) = self.single()?;
+        visitor.visit_enum(EnumDeserializer { value })
+    }
+
+    fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_unit()
+    }
+}
+
+/// Deserializes the value of a single param.
+struct ValueDeserializer<'de> {
+    key: Option<&'de str>,
+    value: &'de str,
+}
+
+impl<'de> Deserializer<'de> for ValueDeserializer<'de> {
+    type Error = PathDeserializationError;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_borrowed_str(self.value)
+    }
+
+    parse_value!(deserialize_bool, visit_bool, "bool");
+    parse_value!(deserialize_i8, visit_i8, "i8");
+    parse_value!(deserialize_i16, visit_i16, "i16");
+    parse_value!(deserialize_i32, visit_i32, "i32");
+    parse_value!(deserialize_i64, visit_i64, "i64");
+    parse_value!(deserialize_i128, visit_i128, "i128");
+    parse_value!(deserialize_u8, visit_u8, "u8");
+    parse_value!(deserialize_u16, visit_u16, "u16");
+    parse_value!(deserialize_u32, visit_u32, "u32");
+    parse_value!(deserialize_u64, visit_u64, "u64");
+    parse_value!(deserialize_u128, visit_u128, "u128");
+    parse_value!(deserialize_f32, visit_f32, "f32");
+    parse_value!(deserialize_f64, visit_f64, "f64");
+    parse_value!(deserialize_char, visit_char, "char");
+
+    fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_borrowed_bytes(self.value.as_bytes())
+    }
+
+    fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_enum(EnumDeserializer { value: self.value })
+    }
+
+    forward_to_deserialize_any! {
+        str string unit unit_struct seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+struct SeqDeserializer<'de> {
+    params: &'de [(String, String)],
+}
+
+impl<'de> SeqAccess<'de> for SeqDeserializer<'de> {
+    type Error = PathDeserializationError;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Self::Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        match self.params.split_first() {
+            Some(((key, value), rest)) => {
+                self.params = rest;
+                seed.deserialize(ValueDeserializer {
+                    key: Some(key),
+                    value,
+                })
+                .map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.params.len())
+    }
+}
+
+struct MapDeserializer<'de> {
+    params: &'de [(String, String)],
+    // 已经读取了 key，等待读取的值
+    value: Option<(&'de str, &'de str)>,
+}
+
+impl<'de> MapAccess<'de> for MapDeserializer<'de> {
+    type Error = PathDeserializationError;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        match self.params.split_first() {
+            Some(((key, value), rest)) => {
+                self.params = rest;
+                self.value = Some((key, value));
+                seed.deserialize(BorrowedStrDeserializer::new(key)).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Self::Error>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        match self.value.take() {
+            Some((key, value)) => seed.deserialize(ValueDeserializer {
+                key: Some(key),
+                value,
+            }),
+            None => Err(PathDeserializationError::custom("value is missing")),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.params.len())
+    }
+}
+
+struct EnumDeserializer<'de> {
+    value: &'de str,
+}
+
+impl<'de> EnumAccess<'de> for EnumDeserializer<'de> {
+    type Error = PathDeserializationError;
+    type Variant = UnitVariant;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant), Self::Error>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let variant = seed.deserialize(BorrowedStrDeserializer::new(self.value))?;
+        Ok((variant, UnitVariant))
+    }
+}
+
+// 路径参数只是字符串，只能表示不带数据的变体
+struct UnitVariant;
+
+impl<'de> VariantAccess<'de> for UnitVariant {
+    type Error = PathDeserializationError;
+
+    fn unit_variant(self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T>(self, _seed: T) -> Result<T::Value, Self::Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        Err(PathDeserializationError::custom("Only unit variants are supported"))
+    }
+
+    fn tuple_variant<V>(self, _len: usize, _visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        Err(PathDeserializationError::custom("Only unit variants are supported"))
+    }
+
+    fn struct_variant<V>(
+        self,
+        _fields: &'static [&'static str],
+        _visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        Err(PathDeserializationError::custom("Only unit variants are supported"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+    use std::collections::HashMap;
+
+    fn params(params: &[(&str, &str)]) -> Vec<(String, String)> {
+        params
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    fn deserialize<'de, T>(params: &'de [(String, String)]) -> Result<T, String>
+    where
+        T: Deserialize<'de>,
+    {
+        T::deserialize(PathDeserializer::new(params)).map_err(|err| err.to_string())
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Member {
+        team: String,
+        user: u32,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    #[serde(rename_all = "lowercase")]
+    enum Kind {
+        Admin,
+        Guest,
+    }
+
+    #[test]
+    fn single_value() {
+        let p = params(&[("id", "42")]);
+        assert_eq!(deserialize::<u32>(&p), Ok(42));
+        assert_eq!(deserialize::<String>(&p), Ok("42".to_owned()));
+        assert_eq!(deserialize::<&str>(&p), Ok("42"));
+        assert_eq!(deserialize::<bool>(&params(&[("on", "true")])), Ok(true));
+        assert_eq!(deserialize::<char>(&params(&[("c", "x")])), Ok('x'));
+        assert_eq!(
+            deserialize::<Kind>(&params(&[("kind", "admin")])),
+            Ok(Kind::Admin)
+        );
+    }
+
+    #[test]
+    fn tuples() {
+        let p = params(&[("team", "a"), ("user", "1")]);
+        assert_eq!(deserialize::<(String, u32)>(&p), Ok(("a".to_owned(), 1)));
+
+        #[derive(Debug, PartialEq, Deserialize)]
+        struct Pair(String, u32);
+        assert_eq!(deserialize::<Pair>(&p), Ok(Pair("a".to_owned(), 1)));
+    }
+
+    #[test]
+    fn structs_and_maps() {
+        let p = params(&[("team", "a"), ("user", "1")]);
+        assert_eq!(
+            deserialize::<Member>(&p),
+            Ok(Member {
+                team: "a".to_owned(),
+                user: 1,
+            })
+        );
+
+        let map = deserialize::<HashMap<String, String>>(&p).unwrap();
+        assert_eq!(map.len(), 2);
+        assert_eq!(map["team"], "a");
+        assert_eq!(map["user"], "1");
+    }
+
+    #[test]
+    fn sequences() {
+        let p = params(&[("a", "1"), ("b", "2"), ("c", "3")]);
+        assert_eq!(deserialize::<Vec<u32>>(&p), Ok(vec![1, 2, 3]));
+        assert_eq!(deserialize::<Vec<u32>>(&[]), Ok(vec![]));
+    }
+
+    #[test]
+    fn options() {
+        assert_eq!(
+            deserialize::<Option<u32>>(&params(&[("id", "7")])),
+            Ok(Some(7))
+        );
+        assert_eq!(deserialize::<Option<u32>>(&[]), Ok(None));
+
+        #[derive(Debug, PartialEq, Deserialize)]
+        struct Page {
+            name: String,
+            page: Option<u32>,
+        }
+        assert_eq!(
+            deserialize::<Page>(&params(&[("name", "docs")])),
+            Ok(Page {
+                name: "docs".to_owned(),
+                page: None,
+            })
+        );
+        assert_eq!(
+            deserialize::<HashMap<String, Option<u32>>>(&params(&[("page", "2")]))
+                .unwrap()
+                .get("page"),
+            Some(&Some(2))
+        );
+    }
+
+    #[test]
+    fn wrong_number_of_params() {
+        let p = params(&[("team", "a"), ("user", "1")]);
+        assert_eq!(
+            deserialize::<u32>(&p),
+            Err("Wrong number of params. Expected 1 but got 2".to_owned())
+        );
+        assert_eq!(
+            deserialize::<(String, u32, u32)>(&p),
+            Err("Wrong number of params. Expected 3 but got 2".to_owned())
+        );
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(
+            deserialize::<u32>(&params(&[("id", "abc")])),
+            Err("Cannot parse `abc` to a `u32` for `id`".to_owned())
+        );
+        assert_eq!(
+            deserialize::<(String, u8)>(&params(&[("team", "a"), ("user", "300")])),
+            Err("Cannot parse `300` to a `u8` for `user`".to_owned())
+        );
+        assert!(deserialize::<Member>(&params(&[("team", "a")]))
+            .unwrap_err()
+            .contains("missing field `user`"));
+        assert!(deserialize::<Kind>(&params(&[("kind", "owner")]))
+            .unwrap_err()
+            .contains("unknown variant `owner`"));
+    }
+
+    #[test]
+    fn only_unit_variants() {
+        #[derive(Debug, Deserialize)]
+        enum Id {
+            #[allow(dead_code)]
+            Number(u32),
+        }
+
+        assert_eq!(
+            deserialize::<Id>(&params(&[("id", "Number")])).unwrap_err(),
+            "Only unit variants are supported"
+        );
+    }
+}
@@ -0,0 +1,216 @@
+mod de;
+
+use super::{FromRequest, InvalidPathParam, MissingRouteParams, PathParamsRejection, RequestParts};
+use crate::router::route::UrlParams;
+use async_trait::async_trait;
+use percent_encoding::percent_decode_str;
+use serde::de::DeserializeOwned;
+use std::ops::{Deref, DerefMut};
+
+/// Extractor that gets the captures of the route the request matched and
+/// deserializes them.
+///
+/// `T` is expected to implement [`serde::Deserialize`].
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use axumlike02::{
+///     extract::Path,
+///     handler::get,
+///     Router,
+/// };
+/// use serde::Deserialize;
+///
+/// // A route with a single capture can use the capture's type directly
+/// async fn user(Path(user_id): Path<u64>) {
+///     // ...
+/// }
+///
+/// // Several captures can be extracted as a tuple, in the order they appear
+/// // in the path
+/// async fn team_member(Path((team_id, user_id)): Path<(u64, u64)>) {
+///     // ...
+/// }
+///
+/// // Or as a struct, by name
+/// #[derive(Deserialize)]
+/// struct Params {
+///     team_id: u64,
+///     user_id: u64,
+/// }
+///
+/// async fn team_member_by_name(Path(params): Path<Params>) {
+///     // ...
+/// }
+///
+/// let app = Router::new()
+///     .route("/users/:user_id", get(user))
+///     .route("/teams/:team_id/users/:user_id", get(team_member))
+///     .route("/by-name/:team_id/:user_id", get(team_member_by_name));
+/// # async {
+/// # axumlike02::Server::bind(&"".parse().unwrap()).serve(app.into_make_service()).await.unwrap();
+/// # };
+/// ```
+///
+/// The captures are percent-decoded before they are deserialized, so
+/// `/users/J%C3%BCrgen` gives `"Jürgen"`. Captures of the routers a route is
+/// nested in are included, before the captures of the route itself.
+///
+/// Optional captures that were left out of the request have no value, so
+/// they can be extracted as an `Option`:
+///
+/// ```rust,no_run
+/// use axumlike02::{extract::Path, handler::get, Router};
+///
+/// async fn page(Path(page): Path<Option<u32>>) -> String {
+///     format!("page {}", page.unwrap_or(1))
+/// }
+///
+/// let app = Router::new().route("/pages/:page?", get(page));
+/// # async {
+/// # axumlike02::Server::bind(&"".parse().unwrap()).serve(app.into_make_service()).await.unwrap();
+/// # };
+/// ```
+///
+/// If the captures can't be deserialized into `T`, or aren't valid UTF-8
+/// after decoding, the request is rejected with `400 Bad Request`. Using
+/// `Path` in a route without any captures is rejected with
+/// `500 Internal Server Error`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Path<T>(pub T);
+
+#[async_trait]
+impl<T, B> FromRequest<B> for Path<T>
+where
+    T: DeserializeOwned + Send,
+    B: Send,
+{
+    type Rejection = PathParamsRejection;
+
+    async fn from_request(req: &mut RequestParts<B>) -> Result<Self, Self::Rejection> {
+        // 没有参数的路由不会插入 UrlParams
+        let params = match req
+            .extensions()
+            .and_then(|ext| ext.get::<Option<UrlParams>>())
+        {
+            Some(Some(params)) => &params.0[..],
+            _ => return Err(MissingRouteParams.into()),
+        };
+
+        let params = params
+            .iter()
+            .map(|(key, value)| {
+                percent_decode_str(value)
+                    .decode_utf8()
+                    .map(|value| (key.to_string(), value.into_owned()))
+                    .map_err(|_| {
+                        InvalidPathParam::new(format!("Invalid UTF-8 in param `{}`", &**key))
+                    })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        T::deserialize(de::PathDeserializer::new(&params))
+            .map(Path)
+            .map_err(|err| InvalidPathParam::new(err.to_string()).into())
+    }
+}
+
+impl<T> Deref for Path<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for Path<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{handler::get, http::StatusCode, test_client::TestClient, Router};
+
+    #[tokio::test]
+    async fn percent_decodes_captures() {
+        let client = TestClient::new(Router::new().route(
+            "/users/:name",
+            get(|Path(name): Path<String>| async { name }),
+        ));
+
+        client
+            .get("/users/J%C3%BCrgen%20M")
+            .send()
+            .await
+            .assert_status(StatusCode::OK)
+            .assert_text("Jürgen M");
+
+        client
+            .get("/users/%FF")
+            .send()
+            .await
+            .assert_status(StatusCode::BAD_REQUEST)
+            .assert_text("Invalid URL param. Invalid UTF-8 in param `name`");
+    }
+
+    #[tokio::test]
+    async fn rejects_invalid_captures() {
+        let client = TestClient::new(Router::new().route(
+            "/users/:id",
+            get(|Path(id): Path<u32>| async move { id.to_string() }),
+        ));
+
+        client.get("/users/7").send().await.assert_text("7");
+
+        client
+            .get("/users/seven")
+            .send()
+            .await
+            .assert_status(StatusCode::BAD_REQUEST)
+            .assert_text("Invalid URL param. Cannot parse `seven` to a `u32` for `id`");
+    }
+
+    #[tokio::test]
+    async fn includes_captures_of_nested_routers() {
+        let users = Router::new().route(
+            "/:user",
+            get(|Path((team, user)): Path<(String, u32)>| async move {
+                format!("{}/{}", team, user)
+            }),
+        );
+        let client = TestClient::new(Router::new().nest("/teams/:team", users));
+
+        client.get("/teams/core/3").send().await.assert_text("core/3");
+    }
+
+    #[tokio::test]
+    async fn optional_captures() {
+        let client = TestClient::new(Router::new().route(
+            "/pages/:page?",
+            get(|Path(page): Path<Option<u32>>| async move { format!("{:?}", page) }),
+        ));
+
+        client.get("/pages/2").send().await.assert_text("Some(2)");
+        client.get("/pages").send().await.assert_text("None");
+    }
+
+    #[tokio::test]
+    async fn route_without_captures() {
+        let client = TestClient::new(
+            Router::new().route("/", get(|_: Path<Option<String>>| async { "unreachable" })),
+        );
+
+        client
+            .get("/")
+            .send()
+            .await
+            .assert_status(StatusCode::INTERNAL_SERVER_ERROR)
+            .assert_text(
+                "No url params found for matched route. This is a bug in axum. Please open an issue",
+            );
+    }
+}
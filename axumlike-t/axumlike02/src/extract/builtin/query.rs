@@ -10,7 +10,7 @@ use std::ops::Deref;
 /// # Example
 ///
 /// ```rust,no_run
-/// use axum::{
+/// use axumlike02::{
 ///     extract::Query,
 ///     handler::get,
 ///     Router,
@@ -33,7 +33,7 @@ use std::ops::Deref;
 ///
 /// let app = Router::new().route("/list_things", get(list_things));
 /// # async {
-/// # axum::Server::bind(&"".parse().unwrap()).serve(app.into_make_service()).await.unwrap();
+/// # axumlike02::Server::bind(&"".parse().unwrap()).serve(app.into_make_service()).await.unwrap();
 /// # };
 /// ```
 ///
@@ -0,0 +1,681 @@
+//! Handle WebSocket connections.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use axumlike02::{
+//!     extract::ws::{Message, WebSocket, WebSocketUpgrade},
+//!     handler::get,
+//!     response::IntoResponse,
+//!     Router,
+//! };
+//!
+//! async fn handler(ws: WebSocketUpgrade) -> impl IntoResponse {
+//!     ws.on_upgrade(handle_socket)
+//! }
+//!
+//! async fn handle_socket(mut socket: WebSocket) {
+//!     while let Some(msg) = socket.recv().await {
+//!         let msg = match msg {
+//!             Ok(msg) => msg,
+//!             // client disconnected
+//!             Err(_) => return,
+//!         };
+//!
+//!         if socket.send(msg).await.is_err() {
+//!             return;
+//!         }
+//!     }
+//! }
+//!
+//! let app = Router::new().route("/ws", get(handler));
+//! # async {
+//! # axumlike02::Server::bind(&"".parse().unwrap()).serve(app.into_make_service()).await.unwrap();
+//! # };
+//! ```
+
+use super::{ExtensionsAlreadyExtracted, FromRequest, HeadersAlreadyExtracted, RequestParts};
+use crate::{response::IntoResponse, BoxError};
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures_util::{
+    sink::{Sink, SinkExt},
+    stream::{Stream, StreamExt},
+};
+use http::{
+    header::{self, HeaderName},
+    HeaderMap, HeaderValue, Method, Response, StatusCode,
+};
+use http_body::Empty;
+use hyper::upgrade::{OnUpgrade, Upgraded};
+use sha1::{Digest, Sha1};
+use std::{
+    borrow::Cow,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tokio_tungstenite::{
+    tungstenite::{
+        self,
+        protocol::{self, WebSocketConfig},
+    },
+    WebSocketStream,
+};
+
+/// Extractor for establishing WebSocket connections.
+///
+/// The request is rejected unless it's a valid WebSocket handshake, that is a
+/// `GET` request with `Connection: upgrade`, `Upgrade: websocket`,
+/// `Sec-WebSocket-Version: 13` and a `Sec-WebSocket-Key`.
+///
+/// See the [module docs](self) for an example.
+#[derive(Debug)]
+pub struct WebSocketUpgrade {
+    config: WebSocketConfig,
+    protocols: Option<Box<[Cow<'static, str>]>>,
+    sec_websocket_key: HeaderValue,
+    sec_websocket_protocol: Option<HeaderValue>,
+    on_upgrade: OnUpgrade,
+}
+
+impl WebSocketUpgrade {
+    /// Set the size of the internal message send queue.
+    pub fn max_send_queue(mut self, max: usize) -> Self {
+        self.config.max_send_queue = Some(max);
+        self
+    }
+
+    /// Set the maximum message size, in bytes.
+    ///
+    /// Defaults to 64 MiB. Receiving a larger message fails and closes the
+    /// connection.
+    pub fn max_message_size(mut self, max: usize) -> Self {
+        self.config.max_message_size = Some(max);
+        self
+    }
+
+    /// Set the maximum frame size, in bytes.
+    ///
+    /// Defaults to 16 MiB.
+    pub fn max_frame_size(mut self, max: usize) -> Self {
+        self.config.max_frame_size = Some(max);
+        self
+    }
+
+    /// Set the known protocols.
+    ///
+    /// If the protocol name specified by `Sec-WebSocket-Protocol` header
+    /// match any of them, the upgrade response will include
+    /// `Sec-WebSocket-Protocol` header and return the protocol name. The
+    /// first protocol in `protocols` that the client also offered is picked.
+    ///
+    /// ```rust,no_run
+    /// use axumlike02::{
+    ///     extract::ws::WebSocketUpgrade,
+    ///     handler::get,
+    ///     response::IntoResponse,
+    ///     Router,
+    /// };
+    ///
+    /// async fn handler(ws: WebSocketUpgrade) -> impl IntoResponse {
+    ///     ws.protocols(["graphql-ws", "graphql-transport-ws"])
+    ///         .on_upgrade(|socket| async move {
+    ///             // `socket.protocol()` is the protocol that was picked, if any
+    ///         })
+    /// }
+    ///
+    /// let app = Router::new().route("/graphql", get(handler));
+    /// # async {
+    /// # axumlike02::Server::bind(&"".parse().unwrap()).serve(app.into_make_service()).await.unwrap();
+    /// # };
+    /// ```
+    pub fn protocols<I>(mut self, protocols: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<Cow<'static, str>>,
+    {
+        self.protocols = Some(
+            protocols
+                .into_iter()
+                .map(Into::into)
+                .collect::<Vec<_>>()
+                .into(),
+        );
+        self
+    }
+
+    /// Finalize upgrading the connection and call the provided callback with
+    /// the stream.
+    ///
+    /// The returned response is the `101 Switching Protocols` response that
+    /// completes the handshake and must be returned from the handler. The
+    /// callback runs in its own task once the connection has been upgraded.
+    pub fn on_upgrade<F, Fut>(self, callback: F) -> impl IntoResponse
+    where
+        F: FnOnce(WebSocket) -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let protocol = self.negotiate_protocol();
+
+        let on_upgrade = self.on_upgrade;
+        let config = self.config;
+        let socket_protocol = protocol.clone();
+
+        tokio::spawn(async move {
+            // 连接在升级完成前断开时没有可以通知的对象
+            let upgraded = match on_upgrade.await {
+                Ok(upgraded) => upgraded,
+                Err(_) => return,
+            };
+
+            let socket =
+                WebSocketStream::from_raw_socket(upgraded, protocol::Role::Server, Some(config))
+                    .await;
+
+            callback(WebSocket {
+                inner: socket,
+                protocol: socket_protocol,
+            })
+            .await;
+        });
+
+        let mut res = Response::new(Empty::<Bytes>::new());
+        *res.status_mut() = StatusCode::SWITCHING_PROTOCOLS;
+
+        let headers = res.headers_mut();
+        headers.insert(header::CONNECTION, HeaderValue::from_static("upgrade"));
+        headers.insert(header::UPGRADE, HeaderValue::from_static("websocket"));
+        headers.insert(
+            header::SEC_WEBSOCKET_ACCEPT,
+            sign(self.sec_websocket_key.as_bytes()),
+        );
+        if let Some(protocol) = protocol {
+            headers.insert(header::SEC_WEBSOCKET_PROTOCOL, protocol);
+        }
+
+        res
+    }
+
+    fn negotiate_protocol(&self) -> Option<HeaderValue> {
+        let protocols = self.protocols.as_ref()?;
+        let requested = self.sec_websocket_protocol.as_ref()?.to_str().ok()?;
+
+        protocols
+            .iter()
+            .find(|protocol| {
+                requested
+                    .split(',')
+                    .any(|requested| requested.trim().eq_ignore_ascii_case(protocol))
+            })
+            .and_then(|protocol| HeaderValue::from_str(protocol).ok())
+    }
+}
+
+#[async_trait]
+impl<B> FromRequest<B> for WebSocketUpgrade
+where
+    B: Send,
+{
+    type Rejection = WebSocketUpgradeRejection;
+
+    async fn from_request(req: &mut RequestParts<B>) -> Result<Self, Self::Rejection> {
+        if req.method() != Method::GET {
+            return Err(MethodNotGet.into());
+        }
+
+        let headers = req.headers().ok_or(HeadersAlreadyExtracted)?;
+
+        if !header_contains(headers, header::CONNECTION, "upgrade") {
+            return Err(InvalidConnectionHeader.into());
+        }
+
+        if !header_eq(headers, header::UPGRADE, "websocket") {
+            return Err(InvalidUpgradeHeader.into());
+        }
+
+        if !header_eq(headers, header::SEC_WEBSOCKET_VERSION, "13") {
+            return Err(InvalidWebSocketVersionHeader.into());
+        }
+
+        let sec_websocket_key = headers
+            .get(header::SEC_WEBSOCKET_KEY)
+            .ok_or(WebSocketKeyHeaderMissing)?
+            .clone();
+
+        let sec_websocket_protocol = headers.get(header::SEC_WEBSOCKET_PROTOCOL).cloned();
+
+        let on_upgrade = req
+            .extensions_mut()
+            .ok_or(ExtensionsAlreadyExtracted)?
+            .remove::<OnUpgrade>()
+            .ok_or(ConnectionNotUpgradable)?;
+
+        Ok(Self {
+            config: Default::default(),
+            protocols: None,
+            sec_websocket_key,
+            sec_websocket_protocol,
+            on_upgrade,
+        })
+    }
+}
+
+fn header_eq(headers: &HeaderMap, key: HeaderName, value: &'static str) -> bool {
+    headers
+        .get(&key)
+        .is_some_and(|header| header.as_bytes().eq_ignore_ascii_case(value.as_bytes()))
+}
+
+// `Connection` 可能包含多个以逗号分隔的值，例如 `keep-alive, Upgrade`
+fn header_contains(headers: &HeaderMap, key: HeaderName, value: &'static str) -> bool {
+    headers
+        .get(&key)
+        .and_then(|header| header.to_str().ok())
+        .is_some_and(|header| {
+            header
+                .split(',')
+                .any(|item| item.trim().eq_ignore_ascii_case(value))
+        })
+}
+
+fn sign(key: &[u8]) -> HeaderValue {
+    let mut sha1 = Sha1::default();
+    sha1.update(key);
+    sha1.update(&b"258EAFA5-E914-47DA-95CA-C5AB0DC85B11"[..]);
+    let accept = base64::encode(sha1.finalize());
+
+    HeaderValue::from_str(&accept).expect("base64 is a valid header value")
+}
+
+/// A stream of WebSocket messages.
+///
+/// Messages can be read with [`WebSocket::recv`] and written with
+/// [`WebSocket::send`], or the socket can be used as a [`Stream`] and a
+/// [`Sink`], for example to split it into separate halves with
+/// [`StreamExt::split`].
+///
+/// Pings are answered automatically, but are still yielded so they can be
+/// observed.
+#[derive(Debug)]
+pub struct WebSocket {
+    inner: WebSocketStream<Upgraded>,
+    protocol: Option<HeaderValue>,
+}
+
+impl WebSocket {
+    /// Receive another message.
+    ///
+    /// Returns `None` if the stream has closed.
+    pub async fn recv(&mut self) -> Option<Result<Message, BoxError>> {
+        self.next().await
+    }
+
+    /// Send a message.
+    pub async fn send(&mut self, msg: Message) -> Result<(), BoxError> {
+        self.inner
+            .send(msg.into_tungstenite())
+            .await
+            .map_err(Into::into)
+    }
+
+    /// Gracefully close this WebSocket.
+    pub async fn close(mut self) -> Result<(), BoxError> {
+        self.inner.close(None).await.map_err(Into::into)
+    }
+
+    /// The subprotocol picked with [`WebSocketUpgrade::protocols`], if any.
+    pub fn protocol(&self) -> Option<&HeaderValue> {
+        self.protocol.as_ref()
+    }
+}
+
+impl Stream for WebSocket {
+    type Item = Result<Message, BoxError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.inner.poll_next_unpin(cx).map(|option_msg| {
+            option_msg.map(|result_msg| {
+                result_msg
+                    .map_err(Into::into)
+                    .map(Message::from_tungstenite)
+            })
+        })
+    }
+}
+
+impl Sink<Message> for WebSocket {
+    type Error = BoxError;
+
+    fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Pin::new(&mut self.inner).poll_ready(cx).map_err(Into::into)
+    }
+
+    fn start_send(mut self: Pin<&mut Self>, item: Message) -> Result<(), Self::Error> {
+        Pin::new(&mut self.inner)
+            .start_send(item.into_tungstenite())
+            .map_err(Into::into)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Pin::new(&mut self.inner).poll_flush(cx).map_err(Into::into)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Pin::new(&mut self.inner).poll_close(cx).map_err(Into::into)
+    }
+}
+
+/// A WebSocket message.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub enum Message {
+    /// A text WebSocket message
+    Text(String),
+    /// A binary WebSocket message
+    Binary(Vec<u8>),
+    /// A ping message with the specified payload
+    ///
+    /// The payload here must have a length less than 125 bytes
+    Ping(Vec<u8>),
+    /// A pong message with the specified payload
+    ///
+    /// The payload here must have a length less than 125 bytes
+    Pong(Vec<u8>),
+    /// A close message with the optional close frame.
+    Close(Option<CloseFrame>),
+}
+
+impl Message {
+    fn into_tungstenite(self) -> tungstenite::Message {
+        match self {
+            Self::Text(text) => tungstenite::Message::Text(text),
+            Self::Binary(binary) => tungstenite::Message::Binary(binary),
+            Self::Ping(ping) => tungstenite::Message::Ping(ping),
+            Self::Pong(pong) => tungstenite::Message::Pong(pong),
+            Self::Close(Some(close)) => {
+                tungstenite::Message::Close(Some(protocol::CloseFrame {
+                    code: protocol::frame::coding::CloseCode::from(close.code),
+                    reason: close.reason,
+                }))
+            }
+            Self::Close(None) => tungstenite::Message::Close(None),
+        }
+    }
+
+    fn from_tungstenite(message: tungstenite::Message) -> Self {
+        match message {
+            tungstenite::Message::Text(text) => Self::Text(text),
+            tungstenite::Message::Binary(binary) => Self::Binary(binary),
+            tungstenite::Message::Ping(ping) => Self::Ping(ping),
+            tungstenite::Message::Pong(pong) => Self::Pong(pong),
+            tungstenite::Message::Close(Some(close)) => Self::Close(Some(CloseFrame {
+                code: close.code.into(),
+                reason: close.reason,
+            })),
+            tungstenite::Message::Close(None) => Self::Close(None),
+        }
+    }
+
+    /// Consume the WebSocket and return it as binary data.
+    pub fn into_data(self) -> Vec<u8> {
+        match self {
+            Self::Text(string) => string.into_bytes(),
+            Self::Binary(data) | Self::Ping(data) | Self::Pong(data) => data,
+            Self::Close(None) => Vec::new(),
+            Self::Close(Some(frame)) => frame.reason.into_owned().into_bytes(),
+        }
+    }
+
+    /// Attempt to consume the WebSocket message and convert it to a String.
+    pub fn into_text(self) -> Result<String, BoxError> {
+        match self {
+            Self::Text(string) => Ok(string),
+            Self::Binary(data) | Self::Ping(data) | Self::Pong(data) => {
+                Ok(String::from_utf8(data)?)
+            }
+            Self::Close(None) => Ok(String::new()),
+            Self::Close(Some(frame)) => Ok(frame.reason.into_owned()),
+        }
+    }
+
+    /// Attempt to get a &str from the WebSocket message,
+    /// this will try to convert binary data to utf8.
+    pub fn to_text(&self) -> Result<&str, BoxError> {
+        match *self {
+            Self::Text(ref string) => Ok(string),
+            Self::Binary(ref data) | Self::Ping(ref data) | Self::Pong(ref data) => {
+                Ok(std::str::from_utf8(data)?)
+            }
+            Self::Close(None) => Ok(""),
+            Self::Close(Some(ref frame)) => Ok(&frame.reason),
+        }
+    }
+}
+
+impl From<String> for Message {
+    fn from(string: String) -> Self {
+        Message::Text(string)
+    }
+}
+
+impl<'s> From<&'s str> for Message {
+    fn from(string: &'s str) -> Self {
+        Message::Text(string.into())
+    }
+}
+
+impl<'b> From<&'b [u8]> for Message {
+    fn from(data: &'b [u8]) -> Self {
+        Message::Binary(data.into())
+    }
+}
+
+impl From<Vec<u8>> for Message {
+    fn from(data: Vec<u8>) -> Self {
+        Message::Binary(data)
+    }
+}
+
+impl From<Message> for Vec<u8> {
+    fn from(msg: Message) -> Self {
+        msg.into_data()
+    }
+}
+
+/// A struct representing the close command.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct CloseFrame {
+    /// The reason as a code.
+    pub code: u16,
+    /// The reason as text string.
+    pub reason: Cow<'static, str>,
+}
+
+define_rejection! {
+    #[status = METHOD_NOT_ALLOWED]
+    #[body = "Request method must be `GET`"]
+    /// Rejection type for [`WebSocketUpgrade`] if the request method isn't
+    /// `GET`.
+    pub struct MethodNotGet;
+}
+
+define_rejection! {
+    #[status = BAD_REQUEST]
+    #[body = "Connection header did not include 'upgrade'"]
+    /// Rejection type for [`WebSocketUpgrade`] if the `Connection` header
+    /// doesn't include `upgrade`.
+    pub struct InvalidConnectionHeader;
+}
+
+define_rejection! {
+    #[status = BAD_REQUEST]
+    #[body = "`Upgrade` header did not include 'websocket'"]
+    /// Rejection type for [`WebSocketUpgrade`] if the `Upgrade` header isn't
+    /// `websocket`.
+    pub struct InvalidUpgradeHeader;
+}
+
+define_rejection! {
+    #[status = BAD_REQUEST]
+    #[body = "`Sec-WebSocket-Version` header did not include '13'"]
+    /// Rejection type for [`WebSocketUpgrade`] if the `Sec-WebSocket-Version`
+    /// header isn't `13`.
+    pub struct InvalidWebSocketVersionHeader;
+}
+
+define_rejection! {
+    #[status = BAD_REQUEST]
+    #[body = "`Sec-WebSocket-Key` header missing"]
+    /// Rejection type for [`WebSocketUpgrade`] if the `Sec-WebSocket-Key`
+    /// header is missing.
+    pub struct WebSocketKeyHeaderMissing;
+}
+
+define_rejection! {
+    #[status = UPGRADE_REQUIRED]
+    #[body = "WebSocket request couldn't be upgraded since no upgrade state was present"]
+    /// Rejection type for [`WebSocketUpgrade`] if the connection can't be
+    /// upgraded, for example because the request didn't come from hyper.
+    pub struct ConnectionNotUpgradable;
+}
+
+composite_rejection! {
+    /// Rejection used for [`WebSocketUpgrade`].
+    ///
+    /// Contains one variant for each way the [`WebSocketUpgrade`] extractor
+    /// can fail.
+    pub enum WebSocketUpgradeRejection {
+        MethodNotGet,
+        InvalidConnectionHeader,
+        InvalidUpgradeHeader,
+        InvalidWebSocketVersionHeader,
+        WebSocketKeyHeaderMissing,
+        ConnectionNotUpgradable,
+        HeadersAlreadyExtracted,
+        ExtensionsAlreadyExtracted,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        handler::{get, on},
+        router::MethodFilter,
+        test_client::TestClient,
+        Router,
+    };
+    use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+
+    const HANDSHAKE: &[(&str, &str)] = &[
+        ("connection", "keep-alive, Upgrade"),
+        ("upgrade", "websocket"),
+        ("sec-websocket-version", "13"),
+        ("sec-websocket-key", "dGhlIHNhbXBsZSBub25jZQ=="),
+    ];
+
+    async fn echo(ws: WebSocketUpgrade) -> impl IntoResponse {
+        ws.protocols(["graphql-ws", "chat"])
+            .on_upgrade(|mut socket| async move {
+                while let Some(Ok(msg)) = socket.recv().await {
+                    if socket.send(msg).await.is_err() {
+                        break;
+                    }
+                }
+            })
+    }
+
+    // 发送握手请求，`replace` 中的请求头替换默认值，值为空时去掉这个请求头
+    async fn handshake(method: Method, replace: &[(&str, &str)]) -> StatusCode {
+        let app = Router::new().route("/ws", on(MethodFilter::GET | MethodFilter::POST, echo));
+        let client = TestClient::new(app);
+
+        let mut req = client.request(method, "/ws");
+        for (key, value) in HANDSHAKE {
+            let value = replace
+                .iter()
+                .find(|(replaced, _)| replaced == key)
+                .map_or(*value, |(_, value)| *value);
+
+            if !value.is_empty() {
+                req = req.header(*key, value);
+            }
+        }
+
+        req.send().await.status()
+    }
+
+    #[test]
+    fn sign_accept_key() {
+        // RFC 6455 第 1.3 节的例子
+        assert_eq!(
+            sign(b"dGhlIHNhbXBsZSBub25jZQ=="),
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_invalid_handshakes() {
+        assert_eq!(
+            handshake(Method::POST, &[]).await,
+            StatusCode::METHOD_NOT_ALLOWED
+        );
+        assert_eq!(
+            handshake(Method::GET, &[("connection", "")]).await,
+            StatusCode::BAD_REQUEST
+        );
+        assert_eq!(
+            handshake(Method::GET, &[("connection", "keep-alive")]).await,
+            StatusCode::BAD_REQUEST
+        );
+        assert_eq!(
+            handshake(Method::GET, &[("upgrade", "h2c")]).await,
+            StatusCode::BAD_REQUEST
+        );
+        assert_eq!(
+            handshake(Method::GET, &[("sec-websocket-version", "8")]).await,
+            StatusCode::BAD_REQUEST
+        );
+        assert_eq!(
+            handshake(Method::GET, &[("sec-websocket-key", "")]).await,
+            StatusCode::BAD_REQUEST
+        );
+    }
+
+    #[tokio::test]
+    async fn requires_upgradable_connection() {
+        // 没有经过 hyper 的请求无法升级
+        assert_eq!(
+            handshake(Method::GET, &[]).await,
+            StatusCode::UPGRADE_REQUIRED
+        );
+    }
+
+    #[tokio::test]
+    async fn echo_over_server() {
+        let app = Router::new().route("/ws", get(echo));
+        let server = crate::Server::bind(&"127.0.0.1:0".parse().unwrap())
+            .serve(app.into_make_service());
+        let addr = server.local_addr();
+        tokio::spawn(server);
+
+        let mut req = format!("ws://{}/ws", addr).into_client_request().unwrap();
+        req.headers_mut().insert(
+            header::SEC_WEBSOCKET_PROTOCOL,
+            HeaderValue::from_static("chat, graphql-ws"),
+        );
+        let (mut socket, res) = tokio_tungstenite::connect_async(req).await.unwrap();
+
+        // 按服务端的顺序选择协议
+        assert_eq!(res.headers()[header::SEC_WEBSOCKET_PROTOCOL], "graphql-ws");
+
+        socket
+            .send(tungstenite::Message::Text("hello".to_owned()))
+            .await
+            .unwrap();
+        assert_eq!(
+            socket.next().await.unwrap().unwrap(),
+            tungstenite::Message::Text("hello".to_owned())
+        );
+    }
+}
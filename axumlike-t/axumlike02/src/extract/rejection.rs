@@ -26,12 +26,21 @@ define_rejection! {
 define_rejection! {
     #[status = BAD_REQUEST]
     #[body = "Failed to parse the request body as JSON"]
-    /// Rejection type for [`Json`](super::Json).
+    /// Rejection type for [`Json`](super::Json) used if the body can't be read
+    /// or isn't valid JSON.
     pub struct InvalidJsonBody(Error);
 }
 
 define_rejection! {
-    #[status = BAD_REQUEST]
+    #[status = UNPROCESSABLE_ENTITY]
+    #[body = "Failed to deserialize the JSON body into the target type"]
+    /// Rejection type for [`Json`](super::Json) used if the body is valid
+    /// JSON but doesn't match the type being extracted.
+    pub struct JsonDataError(Error);
+}
+
+define_rejection! {
+    #[status = UNSUPPORTED_MEDIA_TYPE]
     #[body = "Expected request with `Content-Type: application/json`"]
     /// Rejection type for [`Json`](super::Json) used if the `Content-Type`
     /// header is missing.
@@ -204,6 +213,7 @@ composite_rejection! {
     /// can fail.
     pub enum JsonRejection {
         InvalidJsonBody,
+        JsonDataError,
         MissingJsonContentType,
         BodyAlreadyExtracted,
         HeadersAlreadyExtracted,
@@ -281,6 +291,8 @@ pub enum ContentLengthLimitRejection<T> {
     #[allow(missing_docs)]
     HeadersAlreadyExtracted(HeadersAlreadyExtracted),
     #[allow(missing_docs)]
+    FailedToBufferBody(FailedToBufferBody),
+    #[allow(missing_docs)]
     Inner(T),
 }
 
@@ -296,6 +308,7 @@ where
             Self::PayloadTooLarge(inner) => inner.into_response().map(box_body),
             Self::LengthRequired(inner) => inner.into_response().map(box_body),
             Self::HeadersAlreadyExtracted(inner) => inner.into_response().map(box_body),
+            Self::FailedToBufferBody(inner) => inner.into_response().map(box_body),
             Self::Inner(inner) => inner.into_response().map(box_body),
         }
     }
@@ -310,6 +323,7 @@ where
             Self::PayloadTooLarge(inner) => inner.fmt(f),
             Self::LengthRequired(inner) => inner.fmt(f),
             Self::HeadersAlreadyExtracted(inner) => inner.fmt(f),
+            Self::FailedToBufferBody(inner) => inner.fmt(f),
             Self::Inner(inner) => inner.fmt(f),
         }
     }
@@ -324,6 +338,7 @@ where
             Self::PayloadTooLarge(inner) => Some(inner),
             Self::LengthRequired(inner) => Some(inner),
             Self::HeadersAlreadyExtracted(inner) => Some(inner),
+            Self::FailedToBufferBody(inner) => Some(inner),
             Self::Inner(inner) => Some(inner),
         }
     }
@@ -333,3 +348,10 @@ where
 #[cfg_attr(docsrs, doc(cfg(feature = "headers")))]
 pub use super::builtin::typed_header::TypedHeaderRejection;
 
+#[cfg(feature = "multipart")]
+#[cfg_attr(docsrs, doc(cfg(feature = "multipart")))]
+pub use super::builtin::multipart::{InvalidBoundary, MultipartRejection};
+
+#[cfg(feature = "ws")]
+#[cfg_attr(docsrs, doc(cfg(feature = "ws")))]
+pub use super::builtin::ws::WebSocketUpgradeRejection;
@@ -85,6 +85,46 @@ where
     }
 }
 
+#[async_trait]
+impl<B> FromRequest<B> for Version
+where
+    B: Send,
+{
+    type Rejection = Infallible;
+
+    async fn from_request(req: &mut RequestParts<B>) -> Result<Self, Self::Rejection> {
+        Ok(req.version())
+    }
+}
+
+/// Takes the whole [`HeaderMap`] out of the request, so later extractors that
+/// need the headers are rejected with [`HeadersAlreadyExtracted`].
+#[async_trait]
+impl<B> FromRequest<B> for HeaderMap
+where
+    B: Send,
+{
+    type Rejection = HeadersAlreadyExtracted;
+
+    async fn from_request(req: &mut RequestParts<B>) -> Result<Self, Self::Rejection> {
+        req.take_headers().ok_or(HeadersAlreadyExtracted)
+    }
+}
+
+/// Takes the whole [`Extensions`] out of the request, so later extractors that
+/// need the extensions are rejected with [`ExtensionsAlreadyExtracted`].
+#[async_trait]
+impl<B> FromRequest<B> for Extensions
+where
+    B: Send,
+{
+    type Rejection = ExtensionsAlreadyExtracted;
+
+    async fn from_request(req: &mut RequestParts<B>) -> Result<Self, Self::Rejection> {
+        req.take_extensions().ok_or(ExtensionsAlreadyExtracted)
+    }
+}
+
 
 #[derive(Debug)]
 pub struct BodyStream<B = crate::body::Body>(B);
@@ -135,4 +175,153 @@ where
 
         Ok(string)
     }
-}
\ No newline at end of file
+}
+
+#[async_trait]
+impl<B> FromRequest<B> for Bytes
+where
+    B: http_body::Body + Send,
+    B::Data: Send,
+    B::Error: Into<BoxError>,
+{
+    type Rejection = BytesRejection;
+
+    async fn from_request(req: &mut RequestParts<B>) -> Result<Self, Self::Rejection> {
+        let body = take_body(req)?;
+
+        let bytes = hyper::body::to_bytes(body)
+            .await
+            .map_err(FailedToBufferBody::from_err)?;
+
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        extract::Extension, handler::on, http::StatusCode, router::MethodFilter,
+        test_client::TestClient, Router,
+    };
+
+    fn request_parts() -> RequestParts<crate::body::Body> {
+        let mut req = Request::builder()
+            .version(Version::HTTP_10)
+            .header("x-foo", "foo")
+            .body(crate::body::Body::empty())
+            .unwrap();
+        req.extensions_mut().insert(42_u32);
+        RequestParts::new(req)
+    }
+
+    #[tokio::test]
+    async fn extracts_version() {
+        let mut req = request_parts();
+        assert_eq!(
+            Version::from_request(&mut req).await.unwrap(),
+            Version::HTTP_10
+        );
+    }
+
+    #[tokio::test]
+    async fn take_headers_and_extensions() {
+        let mut req = request_parts();
+
+        let headers = req.take_headers().unwrap();
+        assert_eq!(headers["x-foo"], "foo");
+        assert!(req.headers().is_none());
+        assert!(req.take_headers().is_none());
+
+        let extensions = req.take_extensions().unwrap();
+        assert_eq!(extensions.get::<u32>(), Some(&42));
+        assert!(req.extensions().is_none());
+        assert!(req.take_extensions().is_none());
+    }
+
+    #[tokio::test]
+    async fn extracts_headers_and_extensions_once() {
+        let mut req = request_parts();
+
+        let headers = HeaderMap::from_request(&mut req).await.unwrap();
+        assert_eq!(headers["x-foo"], "foo");
+        assert!(matches!(
+            HeaderMap::from_request(&mut req).await,
+            Err(HeadersAlreadyExtracted)
+        ));
+
+        let extensions = Extensions::from_request(&mut req).await.unwrap();
+        assert_eq!(extensions.get::<u32>(), Some(&42));
+        assert!(matches!(
+            Extensions::from_request(&mut req).await,
+            Err(ExtensionsAlreadyExtracted)
+        ));
+
+        // 头部和扩展被取走后，整个请求也无法再提取
+        assert!(Request::from_request(&mut req).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn already_extracted_rejections() {
+        async fn headers(_: HeaderMap, _: HeaderMap) {}
+        async fn extensions(_: Extensions, _: Extension<u32>) {}
+        async fn bodies(_: Bytes, _: String) {}
+
+        let client = TestClient::new(
+            Router::new()
+                .route("/headers", on(MethodFilter::POST, headers))
+                .route("/extensions", on(MethodFilter::POST, extensions))
+                .route("/bodies", on(MethodFilter::POST, bodies)),
+        );
+
+        client
+            .post("/headers")
+            .send()
+            .await
+            .assert_status(StatusCode::INTERNAL_SERVER_ERROR)
+            .assert_text("Headers taken by other extractor");
+
+        client
+            .post("/extensions")
+            .send()
+            .await
+            .assert_status(StatusCode::INTERNAL_SERVER_ERROR)
+            .assert_text("Extensions taken by other extractor");
+
+        client
+            .post("/bodies")
+            .body("hello")
+            .send()
+            .await
+            .assert_status(StatusCode::INTERNAL_SERVER_ERROR)
+            .assert_text("Cannot have two request body extractors for a single handler");
+    }
+
+    #[tokio::test]
+    async fn bytes_buffers_the_body() {
+        async fn handler(body: Bytes) -> String {
+            format!("{} bytes: {:?}", body.len(), body)
+        }
+
+        let client = TestClient::new(Router::new().route("/", on(MethodFilter::POST, handler)));
+
+        let chunks = futures_util::stream::iter(vec![
+            Ok::<_, Infallible>(Bytes::from("hel")),
+            Ok(Bytes::from("lo")),
+        ]);
+        client
+            .post("/")
+            .body(crate::body::Body::wrap_stream(chunks))
+            .send()
+            .await
+            .assert_status(StatusCode::OK)
+            .assert_text("5 bytes: b\"hello\"");
+
+        client
+            .post("/")
+            .send()
+            .await
+            .assert_status(StatusCode::OK)
+            .assert_text("0 bytes: b\"\"");
+    }
+}
@@ -8,7 +8,7 @@ use std::{
 
 use crate::{
     body::{box_body, BoxBody}, 
-    router::empty_router::EmptyRouter,
+    router::empty_router::{allow_methods, EmptyRouter},
     util::Either,
     extract::FromRequest,
     service::HandleError,
@@ -23,7 +23,7 @@ use http::{Request, Response, StatusCode, Uri};
 
 use async_trait::async_trait;
 
-use crate::router::MethodFilter;
+use crate::router::{MethodFilter, RouteMethods};
 
 use self::into_service::IntoService;
 use tower::ServiceExt;
@@ -32,6 +32,8 @@ mod future;
 
 pub struct OnMethod<H, B, T, F> {
     pub(crate) method: MethodFilter,
+    // 整条链接受的方法
+    pub(crate) methods: MethodFilter,
     pub(crate) handler: H,
     pub(crate) fallback: F,
     pub(crate) _marker: PhantomData<fn() -> (B, T)>,
@@ -41,8 +43,10 @@ pub fn on<H, B, T>(method: MethodFilter, handler: H) -> OnMethod<H, B, T, EmptyR
 where
     H: Handler<B, T>,
 {
+
     OnMethod {
         method,
+        methods: method,
         handler,
         fallback: EmptyRouter::method_not_allowed(),
         _marker: PhantomData,
@@ -87,7 +91,7 @@ pub trait Handler<B, T>: Clone + Send + Sized + 'static {
     /// This allows you to serve a single handler if you don't need any routing:
     ///
     /// ```rust
-    /// use axum::{
+    /// use axumlike02::{
     ///     Server, handler::Handler, http::{Uri, Method}, response::IntoResponse,
     /// };
     /// use tower::make::Shared;
@@ -137,6 +141,7 @@ where
     fn clone(&self) -> Self {
         Self {
             method: self.method,
+            methods: self.methods,
             handler: self.handler.clone(),
             fallback: self.fallback.clone(),
             _marker: PhantomData,
@@ -188,17 +193,17 @@ impl<H, B, T, F> OnMethod<H, B, T, F> {
     /// # Example
     ///
     /// ```rust
-    /// use axum::{handler::post, Router};
+    /// use axumlike02::{handler::on, router::MethodFilter, Router};
     ///
     /// async fn handler() {}
     ///
     /// async fn other_handler() {}
     ///
-    /// // Requests to `GET /` will go to `handler` and `POST /` will go to
+    /// // Requests to `POST /` will go to `handler` and `GET /` will go to
     /// // `other_handler`.
-    /// let app = Router::new().route("/", post(handler).get(other_handler));
+    /// let app = Router::new().route("/", on(MethodFilter::POST, handler).get(other_handler));
     /// # async {
-    /// # axum::Server::bind(&"".parse().unwrap()).serve(app.into_make_service()).await.unwrap();
+    /// # axumlike02::Server::bind(&"".parse().unwrap()).serve(app.into_make_service()).await.unwrap();
     /// # };
     /// ```
     ///
@@ -224,6 +229,10 @@ impl<H, B, T, F> OnMethod<H, B, T, F> {
 
     /// Chain an additional handler that will only accept `OPTIONS` requests.
     ///
+    /// Without one, `OPTIONS` requests are answered with `200 OK` and an
+    /// `Allow` header listing the methods accepted by the chain, the same
+    /// header sent with `405 Method Not Allowed` responses.
+    ///
     /// See [`OnMethod::get`] for an example.
     pub fn options<H2, T2>(self, handler: H2) -> OnMethod<H2, B, T2, Self>
     where
@@ -278,10 +287,10 @@ impl<H, B, T, F> OnMethod<H, B, T, F> {
     /// # Example
     ///
     /// ```rust
-    /// use axum::{
+    /// use axumlike02::{
     ///     handler::get,
     ///     Router,
-    ///     routing::MethodFilter
+    ///     router::MethodFilter
     /// };
     ///
     /// async fn handler() {}
@@ -292,15 +301,17 @@ impl<H, B, T, F> OnMethod<H, B, T, F> {
     /// // `other_handler`
     /// let app = Router::new().route("/", get(handler).on(MethodFilter::DELETE, other_handler));
     /// # async {
-    /// # axum::Server::bind(&"".parse().unwrap()).serve(app.into_make_service()).await.unwrap();
+    /// # axumlike02::Server::bind(&"".parse().unwrap()).serve(app.into_make_service()).await.unwrap();
     /// # };
     /// ```
     pub fn on<H2, T2>(self, method: MethodFilter, handler: H2) -> OnMethod<H2, B, T2, Self>
     where
         H2: Handler<B, T2>,
     {
+
         OnMethod {
             method,
+            methods: method | self.methods,
             handler,
             fallback: self,
             _marker: PhantomData,
@@ -308,6 +319,12 @@ impl<H, B, T, F> OnMethod<H, B, T, F> {
     }
 }
 
+impl<H, B, T, F> RouteMethods for OnMethod<H, B, T, F> {
+    fn methods(&self) -> Option<MethodFilter> {
+        Some(self.methods)
+    }
+}
+
 impl<H, B, T, F> Service<Request<B>> for OnMethod<H, B, T, F>
 where
     H: Handler<B, T>,
@@ -322,13 +339,14 @@ where
         Poll::Ready(Ok(()))
     }
 
-    fn call(&mut self, req: Request<B>) -> Self::Future {
+    fn call(&mut self, mut req: Request<B>) -> Self::Future {
         let req_method = req.method().clone();
 
         let fut = if self.method.matches(req.method()) {
             let fut = Handler::call(self.handler.clone(), req);
             Either::A { inner: fut }
         } else {
+            allow_methods(&mut req, self.method);
             let fut = self.fallback.clone().oneshot(req);
             Either::B { inner: fut }
         };
@@ -1,5 +1,5 @@
 use super::Handler;
-use crate::body::BoxBody;
+use crate::{body::BoxBody, router::RouteMethods};
 use http::{Request, Response};
 use std::{
     convert::Infallible,
@@ -46,6 +46,8 @@ where
     }
 }
 
+impl<H, B, T> RouteMethods for IntoService<H, B, T> {}
+
 impl<H, T, B> Service<Request<B>> for IntoService<H, B, T>
 where
     H: Handler<B, T> + Clone + Send + 'static,
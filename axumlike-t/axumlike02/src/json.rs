@@ -0,0 +1,287 @@
+use crate::{
+    extract::{
+        has_content_type,
+        rejection::{InvalidJsonBody, JsonDataError, JsonRejection, MissingJsonContentType},
+        take_body, FromRequest, RequestParts,
+    },
+    response::IntoResponse,
+    BoxError,
+};
+use async_trait::async_trait;
+use bytes::Bytes;
+use http::{header, HeaderValue, Response, StatusCode};
+use http_body::Full;
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    convert::Infallible,
+    ops::{Deref, DerefMut},
+};
+
+/// JSON Extractor/Response
+///
+/// When used as an extractor, it can deserialize request bodies into some type
+/// that implements [`serde::Deserialize`]. The request is rejected with
+/// `415 Unsupported Media Type` if it doesn't have a JSON `Content-Type`,
+/// such as `application/json` or `application/vnd.api+json`, with
+/// `400 Bad Request` if the body isn't valid JSON, and with
+/// `422 Unprocessable Entity` if it is valid JSON that doesn't match the
+/// type. The rejection says where in the body the error is.
+///
+/// # Extractor example
+///
+/// ```rust,no_run
+/// use axumlike02::{
+///     extract,
+///     handler::on,
+///     router::MethodFilter,
+///     Router,
+/// };
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct CreateUser {
+///     email: String,
+///     password: String,
+/// }
+///
+/// async fn create_user(extract::Json(payload): extract::Json<CreateUser>) {
+///     // payload is a `CreateUser`
+/// }
+///
+/// let app = Router::new().route("/users", on(MethodFilter::POST, create_user));
+/// # async {
+/// # axumlike02::Server::bind(&"".parse().unwrap()).serve(app.into_make_service()).await.unwrap();
+/// # };
+/// ```
+///
+/// When used as a response, it can serialize any type that implements
+/// [`serde::Serialize`] to JSON, and will automatically set the
+/// `Content-Type: application/json` header. If serialization fails the
+/// response is `500 Internal Server Error` with the error as its body.
+///
+/// # Response example
+///
+/// ```rust,no_run
+/// use axumlike02::{
+///     extract::Path,
+///     handler::get,
+///     Json,
+///     Router,
+/// };
+/// use serde::Serialize;
+///
+/// #[derive(Serialize)]
+/// struct User {
+///     id: u64,
+///     username: String,
+/// }
+///
+/// async fn get_user(Path(user_id): Path<u64>) -> Json<User> {
+///     let user = find_user(user_id).await;
+///     Json(user)
+/// }
+///
+/// async fn find_user(user_id: u64) -> User {
+///     // ...
+///     # unimplemented!()
+/// }
+///
+/// let app = Router::new().route("/users/:id", get(get_user));
+/// # async {
+/// # axumlike02::Server::bind(&"".parse().unwrap()).serve(app.into_make_service()).await.unwrap();
+/// # };
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Json<T>(pub T);
+
+#[async_trait]
+impl<T, B> FromRequest<B> for Json<T>
+where
+    T: DeserializeOwned,
+    B: http_body::Body + Send,
+    B::Data: Send,
+    B::Error: Into<BoxError>,
+{
+    type Rejection = JsonRejection;
+
+    async fn from_request(req: &mut RequestParts<B>) -> Result<Self, Self::Rejection> {
+        if !has_content_type(req, "application/json")? {
+            return Err(MissingJsonContentType.into());
+        }
+
+        let body = take_body(req)?;
+        let bytes = hyper::body::to_bytes(body)
+            .await
+            .map_err(InvalidJsonBody::from_err)?;
+
+        // 语法正确但是和类型不符的 JSON 是 422
+        let value = serde_json::from_slice(&bytes).map_err(|err| -> JsonRejection {
+            if err.is_data() {
+                JsonDataError::from_err(err).into()
+            } else {
+                InvalidJsonBody::from_err(err).into()
+            }
+        })?;
+
+        Ok(Json(value))
+    }
+}
+
+impl<T> Deref for Json<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for Json<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl<T> From<T> for Json<T> {
+    fn from(inner: T) -> Self {
+        Self(inner)
+    }
+}
+
+impl<T> IntoResponse for Json<T>
+where
+    T: Serialize,
+{
+    type Body = Full<Bytes>;
+    type BodyError = Infallible;
+
+    fn into_response(self) -> Response<Self::Body> {
+        let bytes = match serde_json::to_vec(&self.0) {
+            Ok(bytes) => bytes,
+            Err(err) => {
+                let mut res = Response::new(Full::from(err.to_string()));
+                *res.status_mut() = StatusCode::INTERNAL_SERVER_ERROR;
+                res.headers_mut()
+                    .insert(header::CONTENT_TYPE, HeaderValue::from_static("text/plain"));
+                return res;
+            }
+        };
+
+        let mut res = Response::new(Full::from(bytes));
+        res.headers_mut().insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("application/json"),
+        );
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{handler::on, router::MethodFilter, test_client::TestClient, Router};
+    use serde::Deserialize;
+    use std::collections::HashMap;
+
+    #[derive(Debug, Deserialize, Serialize)]
+    struct User {
+        name: String,
+    }
+
+    async fn echo(Json(user): Json<User>) -> Json<User> {
+        Json(user)
+    }
+
+    #[tokio::test]
+    async fn accepts_json_content_types() {
+        let client = TestClient::new(Router::new().route("/", on(MethodFilter::POST, echo)));
+
+        for content_type in [
+            "application/json",
+            "application/json; charset=utf-8",
+            "Application/JSON",
+            "application/vnd.api+json",
+            "application/problem+json; charset=utf-8",
+        ] {
+            client
+                .post("/")
+                .header("content-type", content_type)
+                .body(r#"{"name":"bob"}"#)
+                .send()
+                .await
+                .assert_status(StatusCode::OK)
+                .assert_header("content-type", "application/json")
+                .assert_text(r#"{"name":"bob"}"#);
+        }
+    }
+
+    #[tokio::test]
+    async fn rejects_other_content_types() {
+        let client = TestClient::new(Router::new().route("/", on(MethodFilter::POST, echo)));
+
+        client
+            .post("/")
+            .body(r#"{"name":"bob"}"#)
+            .send()
+            .await
+            .assert_status(StatusCode::UNSUPPORTED_MEDIA_TYPE);
+
+        for content_type in [
+            "text/plain",
+            "application/jsonp",
+            "text/json",
+            "application",
+        ] {
+            client
+                .post("/")
+                .header("content-type", content_type)
+                .body(r#"{"name":"bob"}"#)
+                .send()
+                .await
+                .assert_status(StatusCode::UNSUPPORTED_MEDIA_TYPE)
+                .assert_text("Expected request with `Content-Type: application/json`");
+        }
+    }
+
+    #[tokio::test]
+    async fn rejects_invalid_bodies() {
+        let client = TestClient::new(Router::new().route("/", on(MethodFilter::POST, echo)));
+
+        let res = client
+            .post("/")
+            .header("content-type", "application/json")
+            .body(r#"{"name":"#)
+            .send()
+            .await;
+        res.assert_status(StatusCode::BAD_REQUEST);
+        assert!(res
+            .text()
+            .starts_with("Failed to parse the request body as JSON: EOF while parsing"));
+        assert!(res.text().contains("line 1 column 8"));
+
+        let res = client
+            .post("/")
+            .header("content-type", "application/json")
+            .body(r#"{"name":1}"#)
+            .send()
+            .await;
+        res.assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+        assert!(res.text().starts_with(
+            "Failed to deserialize the JSON body into the target type: invalid type: integer `1`"
+        ));
+        assert!(res.text().contains("line 1 column 9"));
+    }
+
+    #[tokio::test]
+    async fn serialize_failure() {
+        // JSON 对象的键只能是字符串
+        let mut map = HashMap::new();
+        map.insert((1, 2), 3);
+
+        let res = Json(map).into_response();
+        assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(res.headers()[header::CONTENT_TYPE], "text/plain");
+
+        let body = hyper::body::to_bytes(res.into_body()).await.unwrap();
+        assert_eq!(body, "key must be a string");
+    }
+}
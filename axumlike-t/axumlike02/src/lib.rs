@@ -10,14 +10,19 @@ mod macros;
 pub mod router;
 mod body;
 mod error;
+mod json;
 pub mod util;
 pub mod handler;
 pub mod response;
 pub mod buffer;
 pub mod extract;
 pub mod service;
+pub mod serve;
+#[cfg(any(test, feature = "test-client"))]
+#[cfg_attr(docsrs, doc(cfg(feature = "test-client")))]
+pub mod test_client;
 
-pub use self::router::Router;
+pub use self::{json::Json, router::Router};
 
 /// Alias for a type-erased error type.
 pub type BoxError = Box<dyn std::error::Error + Send + Sync>;
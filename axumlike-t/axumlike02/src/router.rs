@@ -2,11 +2,17 @@ pub mod empty_router;
 pub mod route;
 pub mod future;
 pub mod method_filter;
+mod constraint;
+mod host;
+mod named_routes;
+mod trailing_slash;
+mod tree;
+#[cfg(test)]
+mod tests;
 
-use crate::buffer::MpscBuffer;
+use crate::buffer::{self, MpscBuffer};
 
 use std::{
-    borrow::Cow,
     convert::Infallible,
     fmt,
     future::ready,
@@ -19,6 +25,7 @@ use crate::body::{box_body, BoxBody,};
 
 use crate::service::HandleError;
 
+use bytes::Bytes;
 use http::{Request, Response, StatusCode, Uri};
 use tower::{
     util::{BoxService, ServiceExt},
@@ -32,16 +39,33 @@ use crate::BoxError;
 
 use self::{
     empty_router::{EmptyRouter, FromEmptyRouter},
-    route::{PathPattern, Route},
-    future::{EmptyRouterFuture, RouteFuture},
+    route::{MatchedRoutes, Nested, PathPattern, Registration, Route, RouteId, RouteKind, Routes},
+    future::{BoxRouteFuture, EmptyRouterFuture, FallbackFuture, MergeFuture, RouteFuture},
+    trailing_slash::Normalized,
+    tree::{InsertError, Node},
 };
 
-pub use self::method_filter::MethodFilter;
+pub use self::{
+    host::{Host, HostRouter},
+    method_filter::{MethodFilter, RouteMethods},
+    named_routes::{NamedRoutes, UrlForError},
+    trailing_slash::TrailingSlash,
+};
 
 #[derive(Debug, Clone)]
 pub struct Router<S> {
     // 代表 Service
     svc: S,
+    // 所有路由共用一棵前缀树，每个请求只在这里匹配一次
+    node: Arc<Node>,
+    // 注册过的路由，合并路由器时重新插入另一棵树
+    registrations: Arc<Vec<Registration>>,
+    // 有名称的路由，通过请求的 extension 传给 handler
+    named: NamedRoutes,
+    // 是否已经设置了 fallback，合并后只能保留一个
+    has_fallback: bool,
+    // 没有设置时使用外层路由器的策略
+    trailing_slash: Option<TrailingSlash>,
 }
 
 impl<E> Router<EmptyRouter<E>> {
@@ -49,6 +73,11 @@ impl<E> Router<EmptyRouter<E>> {
     pub fn new() -> Self {
         Self {
             svc: EmptyRouter::not_found(),
+            node: Arc::new(Node::default()),
+            registrations: Arc::new(Vec::new()),
+            named: NamedRoutes::default(),
+            has_fallback: false,
+            trailing_slash: None,
         }
     }
 }
@@ -59,10 +88,12 @@ impl<E> Default for Router<EmptyRouter<E>> {
     }
 }
 
+impl<S> RouteMethods for Router<S> {}
+
 // 为 Router 实现 Service
-impl<S, R> Service<R> for Router<S>
+impl<S, B> Service<Request<B>> for Router<S>
 where
-    S: Service<R>,
+    S: Service<Request<B>>,
 {
     type Response = S::Response;
     type Error = S::Error;
@@ -74,32 +105,530 @@ where
     }
 
     #[inline]
-    fn call(&mut self, req: R) -> Self::Future {
+    fn call(&mut self, mut req: Request<B>) -> Self::Future {
+        let trailing_slash = match self.trailing_slash {
+            Some(trailing_slash) => {
+                req.extensions_mut().insert(trailing_slash);
+                trailing_slash
+            }
+            None => req
+                .extensions()
+                .get::<TrailingSlash>()
+                .copied()
+                .unwrap_or_default(),
+        };
+
+        match trailing_slash::normalize(&self.node, trailing_slash, &mut req) {
+            // 重定向的请求不匹配任何路由，由 EmptyRouter 返回 308
+            Normalized::Redirect => {
+                req.extensions_mut().insert(MatchedRoutes(Vec::new()));
+            }
+            Normalized::Route(found) => route::match_request(&mut req, found),
+        }
+        route::insert_named_routes(&self.named, &mut req);
         self.svc.call(req)
     }
 }
 
 impl<S> Router<S> {
-    pub fn route<T>(self, description: &str, svc: T) -> Router<Route<T, S>> {
+    /// Add another route to the router.
+    ///
+    /// `description` is a path made of:
+    ///
+    /// - Literal segments, such as `/users`.
+    /// - `:name` captures matching a single segment, such as `/users/:id`.
+    /// - Constrained captures, such as `/users/:id<u64>`, that only match
+    ///   segments parsing as an integer type (`u8` to `u128`, `i8` to `i128`,
+    ///   `usize` or `isize`), as a `uuid`, or matching a regular expression,
+    ///   such as `/posts/:slug<[a-z-]+>`. Segments are percent-decoded before
+    ///   they're checked. Constraints cannot contain `/`.
+    /// - A `*name` catch-all as the last segment, matching the rest of the
+    ///   path including any `/`, such as `/assets/*path`. It doesn't match an
+    ///   empty rest, so `/assets/*path` doesn't match `/assets` or `/assets/`.
+    /// - Optional trailing captures marked with `?`, such as
+    ///   `/archive/:year?/:month?`, which also matches `/archive` and
+    ///   `/archive/2021`. An optional catch-all such as `/assets/*path?` also
+    ///   matches `/assets` and `/assets/`, without capturing `path`.
+    ///
+    /// The captured values are available to extractors as URL params.
+    ///
+    /// When several routes could match a path, literal segments are preferred
+    /// over constrained captures, then `:name` captures and then catch-alls,
+    /// regardless of the order the routes were added in. So `/users/:id<u64>`
+    /// and `/users/:name` can both be added, and `/users/42` goes to the
+    /// first one while `/users/bob` goes to the second one. Constrained
+    /// captures at the same position are tried in the order they were added.
+    ///
+    /// Adding the same path several times merges the methods of each route:
+    ///
+    /// ```rust,no_run
+    /// use axumlike02::{handler::{get, on}, router::MethodFilter, Router};
+    ///
+    /// async fn list_users() {}
+    ///
+    /// async fn create_user() {}
+    ///
+    /// // Same as `.route("/users", get(list_users).post(create_user))`.
+    /// let app = Router::new()
+    ///     .route("/users", get(list_users))
+    ///     .route("/users", on(MethodFilter::POST, create_user));
+    /// # async {
+    /// # axumlike02::Server::bind(&"".parse().unwrap()).serve(app.into_make_service()).await.unwrap();
+    /// # };
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `description` doesn't start with `/`, if a catch-all isn't the
+    /// last segment, if a non-optional segment follows an optional one, or if a
+    /// capture conflicts with a capture of another name and the same
+    /// constraint at the same position, such as `/users/:id` and
+    /// `/users/:name/posts`, or if a constraint is an invalid regular
+    /// expression.
+    ///
+    /// Also panics if another route for the same path already accepts one of
+    /// the methods of `svc`, since one of them would never be called. The
+    /// methods come from [`RouteMethods`], so only the routes built with the
+    /// method helpers, such as [`handler::get`] or [`service::post`], and
+    /// [`ServeDir`] are checked, other services may accept any method. An
+    /// explicit `HEAD` route and a `GET` route can be added in either order,
+    /// the `HEAD` route then gets the `HEAD` requests.
+    ///
+    /// [`handler::get`]: crate::handler::get
+    /// [`service::post`]: crate::service::post
+    /// [`ServeDir`]: crate::service::ServeDir
+    pub fn route<T>(self, description: &str, svc: T) -> Router<Route<T, S>>
+    where
+        T: RouteMethods,
+    {
+        self.add_route(None, description, svc)
+    }
+
+    /// Add another route to the router, with a name that can be used to build
+    /// its path with [`NamedRoutes::url_for`].
+    ///
+    /// ```rust,no_run
+    /// use axumlike02::{handler::get, Router};
+    ///
+    /// let app = Router::new()
+    ///     .named_route("user_detail", "/users/:id", get(|| async {}));
+    ///
+    /// let url = app.named_routes().url_for("user_detail", &[("id", "42")]);
+    /// assert_eq!(url.unwrap(), "/users/42");
+    /// # async {
+    /// # axumlike02::Server::bind(&"".parse().unwrap()).serve(app.into_make_service()).await.unwrap();
+    /// # };
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics for the same reasons as [`Router::route`], or if another route
+    /// already has the same name.
+    pub fn named_route<T>(self, name: &str, description: &str, svc: T) -> Router<Route<T, S>>
+    where
+        T: RouteMethods,
+    {
+        self.add_route(Some(name), description, svc)
+    }
+
+    fn add_route<T>(mut self, name: Option<&str>, description: &str, svc: T) -> Router<Route<T, S>>
+    where
+        T: RouteMethods,
+    {
+        let id = RouteId::next();
+        let methods = svc.methods();
+        let pattern = PathPattern::new(description);
+        let captures = pattern.has_captures();
+        let registration = Registration {
+            id,
+            pattern,
+            kind: RouteKind::Route(methods),
+            name: name.map(ToOwned::to_owned),
+        };
+
+        if let Err(err) = self.register(registration) {
+            panic!("Invalid route `{}`: {}", description, err);
+        }
+
+        let may_decline = methods.is_none();
+        let fallback_below = self.has_fallback;
+
         self.map(|fallback| Route {
-            pattern: PathPattern::new(description),
+            id,
+            svc,
+            fallback,
+            may_decline,
+            fallback_below,
+            captures,
+        })
+    }
+
+    /// Nest a group of routes (or a [`Service`]) at some path.
+    ///
+    /// The matched prefix is stripped from the URI before the request reaches
+    /// `svc`, so a nested router only has to know about its own paths:
+    ///
+    /// ```rust,no_run
+    /// use axumlike02::{handler::get, Router};
+    ///
+    /// async fn users() -> &'static str {
+    ///     "users"
+    /// }
+    ///
+    /// let api = Router::new().route("/users", get(users));
+    ///
+    /// // `GET /api/users` will be handled by `users`.
+    /// let app = Router::new().nest("/api", api);
+    /// # async {
+    /// # axumlike02::Server::bind(&"".parse().unwrap()).serve(app.into_make_service()).await.unwrap();
+    /// # };
+    /// ```
+    ///
+    /// The prefix may contain captures, such as `/users/:id`. They are added
+    /// to the URL params together with the ones captured by the nested router.
+    ///
+    /// If `svc` doesn't have a route for the request, the original request is
+    /// passed on to the rest of this router, ending with its 404 response.
+    ///
+    /// Routes of this router matching a path below the prefix take priority
+    /// over the nested service, unless none of them accepts the method of the
+    /// request. So with a `GET /api/users` route and a router nested at `/api`
+    /// with a `POST /users` route, `POST /api/users` goes to the nested router.
+    ///
+    /// # Panics
+    ///
+    /// Panics for the same reasons as [`Router::route`], or if `description`
+    /// contains a catch-all or optional segments.
+    pub fn nest<T>(mut self, description: &str, svc: T) -> Router<Nested<T, S>> {
+        let pattern = PathPattern::new(description);
+        let id = RouteId::next();
+
+        assert!(
+            !pattern.is_open_ended(),
+            "Invalid route `{}`: nested paths cannot contain catch-all or optional segments",
+            description
+        );
+
+        let registration = Registration {
+            id,
+            pattern: pattern.clone(),
+            kind: RouteKind::Nested,
+            name: None,
+        };
+
+        if let Err(err) = self.register(registration) {
+            panic!("Invalid route `{}`: {}", description, err);
+        }
+
+        let fallback_below = self.has_fallback;
+
+        self.map(|fallback| Nested {
+            id,
+            pattern,
             svc,
             fallback,
+            fallback_below,
         })
     }
 
+    /// Add a fallback service to the router.
+    ///
+    /// The fallback is called with the original request whenever no route
+    /// matches its path, instead of responding with an empty `404 Not Found`.
+    /// Requests whose path matches but whose method doesn't are still rejected
+    /// with `405 Method Not Allowed`.
+    ///
+    /// Handlers can be used as fallbacks with [`Handler::into_service`]:
+    ///
+    /// ```rust,no_run
+    /// use axumlike02::{
+    ///     handler::{get, Handler},
+    ///     http::{StatusCode, Uri},
+    ///     response::IntoResponse,
+    ///     Router,
+    /// };
+    ///
+    /// async fn fallback(uri: Uri) -> impl IntoResponse {
+    ///     (StatusCode::NOT_FOUND, format!("No route for {}", uri))
+    /// }
+    ///
+    /// let app = Router::new()
+    ///     .route("/", get(|| async {}))
+    ///     .fallback(fallback.into_service());
+    /// # async {
+    /// # axumlike02::Server::bind(&"".parse().unwrap()).serve(app.into_make_service()).await.unwrap();
+    /// # };
+    /// ```
+    ///
+    /// The fallback is only used for requests that reach this router. A
+    /// router nested with [`Router::nest`] answers unmatched requests with its
+    /// own fallback if it has one, and otherwise hands them back to the outer
+    /// router.
+    ///
+    /// [`Handler::into_service`]: crate::handler::Handler::into_service
+    pub fn fallback<T>(mut self, svc: T) -> Router<Fallback<T, S>> {
+        self.has_fallback = true;
+
+        self.map(|inner| Fallback {
+            inner,
+            fallback: svc,
+        })
+    }
+
+    /// Merge the routes of another router into this one.
+    ///
+    /// This allows routers built separately, for example in different
+    /// modules, to be served together:
+    ///
+    /// ```rust,no_run
+    /// use axumlike02::{handler::get, Router};
+    ///
+    /// let users = Router::new().route("/users", get(|| async { "users" }));
+    /// let teams = Router::new().route("/teams", get(|| async { "teams" }));
+    ///
+    /// let app = users.merge(teams);
+    /// # async {
+    /// # axumlike02::Server::bind(&"".parse().unwrap()).serve(app.into_make_service()).await.unwrap();
+    /// # };
+    /// ```
+    ///
+    /// The routes of both routers are matched as if they had been added to a
+    /// single router, so the methods of routes with the same path are merged
+    /// the same way as with [`Router::route`].
+    ///
+    /// Which route handles a request is decided by matching the routes of
+    /// both routers together, not by the order of the routers. If one of the
+    /// routers has a fallback, it's only called when neither router has a
+    /// route for the request, whether it was set on `self` or on `other`.
+    ///
+    /// A [`TrailingSlash`] policy set on either router applies to the merged
+    /// router.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the routes of `other` conflict with the ones of `self`, for
+    /// the same reasons as [`Router::route`], if both routers have a
+    /// fallback, or if they have different [`TrailingSlash`] policies.
+    pub fn merge<T>(mut self, other: Router<T>) -> Router<Merge<S, T>> {
+        assert!(
+            !(self.has_fallback && other.has_fallback),
+            "Cannot merge two routers that both have a fallback"
+        );
+
+        if let (Some(ours), Some(theirs)) = (self.trailing_slash, other.trailing_slash) {
+            assert!(
+                ours == theirs,
+                "Cannot merge routers with different trailing slash policies: {:?} and {:?}",
+                ours,
+                theirs
+            );
+        }
+
+        for registration in other.registrations.iter() {
+            if let Err(err) = self.register(registration.clone()) {
+                panic!(
+                    "Cannot merge route `{}`: {}",
+                    registration.pattern.as_str(),
+                    err
+                );
+            }
+        }
+
+        // 有 fallback 的路由器放到最后，其他路由器才有机会处理请求
+        let reversed = self.has_fallback;
+        self.has_fallback |= other.has_fallback;
+        self.trailing_slash = self.trailing_slash.or(other.trailing_slash);
+
+        self.map(|first| Merge {
+            first,
+            second: other.svc,
+            reversed,
+        })
+    }
+
+    /// Erase the type of the router's service.
+    ///
+    /// Every call to [`Router::route`] wraps the service in another layer, so
+    /// the type of a router grows with the number of routes. Boxing the
+    /// router gives it a short, nameable type that can be stored in structs
+    /// and returned from functions, and keeps compile times down for large
+    /// applications:
+    ///
+    /// ```rust,no_run
+    /// use axumlike02::{
+    ///     handler::get,
+    ///     router::{BoxRoute, Router},
+    /// };
+    ///
+    /// fn api_routes() -> Router<BoxRoute> {
+    ///     Router::new()
+    ///         .route("/users", get(|| async { "users" }))
+    ///         .route("/teams", get(|| async { "teams" }))
+    ///         .boxed()
+    /// }
+    /// # async {
+    /// let app = Router::new().nest("/api", api_routes());
+    /// # axumlike02::Server::bind(&"".parse().unwrap()).serve(app.into_make_service()).await.unwrap();
+    /// # };
+    /// ```
+    ///
+    /// The boxed service runs in a background task, which is spawned on the
+    /// current Tokio runtime, so this must be called from within a runtime.
+    /// Requests are sent to it through an [`MpscBuffer`] with the default
+    /// configuration, use [`Router::boxed_with`] to configure it.
+    pub fn boxed<ReqBody, ResBody>(self) -> Router<BoxRoute<ReqBody, S::Error>>
+    where
+        S: Service<Request<ReqBody>, Response = Response<ResBody>> + Send + 'static,
+        S::Error: Into<BoxError> + Send,
+        S::Future: Send,
+        ReqBody: Send + 'static,
+        ResBody: http_body::Body<Data = Bytes> + Send + Sync + 'static,
+        ResBody::Error: Into<BoxError>,
+    {
+        self.boxed_with(&buffer::Builder::new())
+    }
+
+    /// Erase the type of the router's service, like [`Router::boxed`], with
+    /// a buffer configured by `buffer`.
+    ///
+    /// Requests rejected because the buffer is full, or sent after the
+    /// background task stopped, get an empty `503 Service Unavailable`
+    /// response.
+    pub fn boxed_with<ReqBody, ResBody>(
+        self,
+        buffer: &buffer::Builder,
+    ) -> Router<BoxRoute<ReqBody, S::Error>>
+    where
+        S: Service<Request<ReqBody>, Response = Response<ResBody>> + Send + 'static,
+        S::Error: Into<BoxError> + Send,
+        S::Future: Send,
+        ReqBody: Send + 'static,
+        ResBody: http_body::Body<Data = Bytes> + Send + Sync + 'static,
+        ResBody::Error: Into<BoxError>,
+    {
+        self.map(|svc| {
+            ServiceBuilder::new()
+                .layer_fn(BoxRoute)
+                .layer_fn(|svc| buffer.build(svc))
+                .layer(BoxService::layer())
+                .layer(MapResponseBodyLayer::new(box_body))
+                .service(svc)
+        })
+    }
+
+    /// Set how the router handles paths that only differ from a route by a
+    /// trailing slash, such as `/users/` for a `/users` route, or by duplicate
+    /// slashes, such as `//users`.
+    ///
+    /// The default is [`TrailingSlash::Strict`], where only duplicate slashes
+    /// are ignored and paths with a different trailing slash don't match.
+    /// With [`TrailingSlash::Redirect`] clients are redirected to the path of
+    /// the route, and with [`TrailingSlash::Ignore`] the route is called as if
+    /// the request was sent to its path:
+    ///
+    /// ```rust,no_run
+    /// use axumlike02::{
+    ///     handler::get,
+    ///     router::{Router, TrailingSlash},
+    /// };
+    ///
+    /// // `GET /users/` and `GET //users` redirect to `/users`.
+    /// let app = Router::new()
+    ///     .route("/users", get(|| async {}))
+    ///     .trailing_slash(TrailingSlash::Redirect);
+    /// # async {
+    /// # axumlike02::Server::bind(&"".parse().unwrap()).serve(app.into_make_service()).await.unwrap();
+    /// # };
+    /// ```
+    ///
+    /// Duplicate slashes are always collapsed, but a trailing slash is only
+    /// added or removed when the path doesn't match a route as it is, so
+    /// `/files/a/` still matches `/files/*path` unchanged. Routers added with
+    /// [`Router::nest`] use the policy of the router they are nested in,
+    /// unless they set their own.
+    pub fn trailing_slash(mut self, policy: TrailingSlash) -> Self {
+        self.trailing_slash = Some(policy);
+        self
+    }
+
+    /// The routes added to the router, in the order they were added.
+    ///
+    /// This includes the routes of merged routers, but not the routes of
+    /// services added with [`Router::nest`], which are listed once with the
+    /// prefix they were nested at:
+    ///
+    /// ```rust,no_run
+    /// use axumlike02::{handler::get, Router};
+    ///
+    /// let app = Router::new()
+    ///     .route("/", get(|| async {}))
+    ///     .route("/users/:id", get(|| async {}).post(|| async {}));
+    ///
+    /// for route in app.routes() {
+    ///     println!("{:?} {}", route.methods(), route.path());
+    /// }
+    /// # async {
+    /// # axumlike02::Server::bind(&"".parse().unwrap()).serve(app.into_make_service()).await.unwrap();
+    /// # };
+    /// ```
+    pub fn routes(&self) -> Routes<'_> {
+        Routes {
+            inner: self.registrations.iter(),
+        }
+    }
+
+    /// The routes of the router that were added with a name.
+    ///
+    /// Handlers can get them from the request extensions. See [`NamedRoutes`]
+    /// for more details.
+    pub fn named_routes(&self) -> &NamedRoutes {
+        &self.named
+    }
+
+    fn register(&mut self, registration: Registration) -> Result<(), InsertError> {
+        if let Some(name) = &registration.name {
+            if self.named.get(name).is_some() {
+                return Err(InsertError::DuplicateName(name.clone()));
+            }
+        }
+
+        let node = Arc::make_mut(&mut self.node);
+
+        match registration.kind {
+            RouteKind::Route(methods) => {
+                node.insert(&registration.pattern, registration.id, methods)?
+            }
+            RouteKind::Nested => node.insert_prefix(&registration.pattern, registration.id)?,
+        }
+
+        if let Some(name) = &registration.name {
+            self.named
+                .insert(name.clone(), registration.pattern.clone());
+        }
+
+        Arc::make_mut(&mut self.registrations).push(registration);
+        Ok(())
+    }
+
     fn map<F, S2>(self, f: F) -> Router<S2>
     where
         F: FnOnce(S) -> S2,
     {
-        Router { svc: f(self.svc) }
+        Router {
+            svc: f(self.svc),
+            node: self.node,
+            registrations: self.registrations,
+            named: self.named,
+            has_fallback: self.has_fallback,
+            trailing_slash: self.trailing_slash,
+        }
     }
 
-    pub fn into_make_service(self) -> IntoMakeService<S>
+    pub fn into_make_service(self) -> IntoMakeService<Self>
     where
         S: Clone,
     {
-        IntoMakeService::new(self.svc)
+        IntoMakeService::new(self)
     }
 
     // 使用 tower_service::Layer 为 route 增加中间件支持
@@ -232,4 +761,110 @@ where
     fn call(&mut self, req: R) -> Self::Future {
         self.0.call(req)
     }
-}
\ No newline at end of file
+}
+
+/// A [`Service`] that calls a fallback service for requests no route matched.
+///
+/// Created with [`Router::fallback`]. See that method for more details.
+#[derive(Debug, Clone)]
+pub struct Fallback<T, S> {
+    inner: S,
+    fallback: T,
+}
+
+impl<T, S, B> Service<Request<B>> for Fallback<T, S>
+where
+    S: Service<Request<B>, Response = Response<BoxBody>> + Clone,
+    T: Service<Request<B>, Response = Response<BoxBody>, Error = S::Error> + Clone,
+    B: Send + Sync + 'static,
+{
+    type Response = Response<BoxBody>;
+    type Error = S::Error;
+    type Future = FallbackFuture<S, T, B>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: Request<B>) -> Self::Future {
+        FallbackFuture::new(self.inner.clone().oneshot(req), self.fallback.clone())
+    }
+}
+
+/// A [`Service`] combining the routes of two routers.
+///
+/// Created with [`Router::merge`]. See that method for more details.
+#[derive(Debug, Clone)]
+pub struct Merge<S, T> {
+    first: S,
+    second: T,
+    // `first` 有 fallback 时先调用 `second`
+    reversed: bool,
+}
+
+impl<S, T, B> Service<Request<B>> for Merge<S, T>
+where
+    S: Service<Request<B>, Response = Response<BoxBody>> + Clone,
+    T: Service<Request<B>, Response = Response<BoxBody>, Error = S::Error> + Clone,
+    B: Send + Sync + 'static,
+{
+    type Response = Response<BoxBody>;
+    type Error = S::Error;
+    type Future = MergeFuture<S, T, B>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: Request<B>) -> Self::Future {
+        if self.reversed {
+            let fut = self.second.clone().oneshot(req);
+            MergeFuture::reversed(RouteFuture::a(fut, self.first.clone()))
+        } else {
+            let fut = self.first.clone().oneshot(req);
+            MergeFuture::forward(RouteFuture::a(fut, self.second.clone()))
+        }
+    }
+}
+
+/// A boxed route trait object.
+///
+/// See [`Router::boxed`] for more details.
+pub struct BoxRoute<B = crate::body::Body, E = Infallible>(
+    MpscBuffer<BoxService<Request<B>, Response<BoxBody>, E>, Request<B>>,
+);
+
+impl<B, E> Clone for BoxRoute<B, E> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<B, E> fmt::Debug for BoxRoute<B, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BoxRoute").finish()
+    }
+}
+
+impl<B, E> RouteMethods for BoxRoute<B, E> {}
+
+impl<B, E> Service<Request<B>> for BoxRoute<B, E>
+where
+    E: Into<BoxError>,
+{
+    type Response = Response<BoxBody>;
+    type Error = E;
+    type Future = BoxRouteFuture<B, E>;
+
+    #[inline]
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    #[inline]
+    fn call(&mut self, req: Request<B>) -> Self::Future {
+        BoxRouteFuture {
+            inner: self.0.clone().oneshot(req),
+        }
+    }
+}
@@ -0,0 +1,111 @@
+// 路由参数的约束，例如 `:id<u64>` 或 `:slug<[a-z-]+>`
+//
+// 不满足约束的路径段不会匹配这个参数，路由树会继续尝试其他分支。
+use percent_encoding::percent_decode_str;
+use regex::Regex;
+use std::{fmt, str::FromStr};
+
+#[derive(Clone)]
+pub(crate) struct Constraint {
+    source: String,
+    kind: Kind,
+}
+
+#[derive(Clone)]
+enum Kind {
+    // 内置的类型，检查能否解析
+    Builtin(fn(&str) -> bool),
+    Regex(Regex),
+}
+
+impl Constraint {
+    /// Parse the part of a capture between `<` and `>`.
+    ///
+    /// The names of integer types and `uuid` are built in, anything else is
+    /// a regular expression that must match the whole segment.
+    pub(crate) fn new(source: &str) -> Result<Self, regex::Error> {
+        let kind = match source {
+            "u8" => Kind::Builtin(parses::<u8>),
+            "u16" => Kind::Builtin(parses::<u16>),
+            "u32" => Kind::Builtin(parses::<u32>),
+            "u64" => Kind::Builtin(parses::<u64>),
+            "u128" => Kind::Builtin(parses::<u128>),
+            "usize" => Kind::Builtin(parses::<usize>),
+            "i8" => Kind::Builtin(parses::<i8>),
+            "i16" => Kind::Builtin(parses::<i16>),
+            "i32" => Kind::Builtin(parses::<i32>),
+            "i64" => Kind::Builtin(parses::<i64>),
+            "i128" => Kind::Builtin(parses::<i128>),
+            "isize" => Kind::Builtin(parses::<isize>),
+            "uuid" => Kind::Builtin(is_uuid),
+            _ => Kind::Regex(Regex::new(&format!("^(?:{})$", source))?),
+        };
+
+        Ok(Self {
+            source: source.to_owned(),
+            kind,
+        })
+    }
+
+    pub(crate) fn matches(&self, segment: &str) -> bool {
+        match &self.kind {
+            Kind::Builtin(matches) => matches(segment),
+            Kind::Regex(regex) => regex.is_match(segment),
+        }
+    }
+}
+
+// 两个约束的写法相同时才认为是同一个约束
+impl PartialEq for Constraint {
+    fn eq(&self, other: &Self) -> bool {
+        self.source == other.source
+    }
+}
+
+impl Eq for Constraint {}
+
+impl fmt::Debug for Constraint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Constraint").field(&self.source).finish()
+    }
+}
+
+impl fmt::Display for Constraint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<{}>", self.source)
+    }
+}
+
+/// Whether a `:name` capture with an optional constraint matches `segment`.
+///
+/// Constraints are checked against the percent-decoded segment, which is what
+/// extractors see.
+pub(crate) fn param_matches(constraint: Option<&Constraint>, segment: &str) -> bool {
+    if segment.is_empty() {
+        return false;
+    }
+
+    match constraint {
+        Some(constraint) => percent_decode_str(segment)
+            .decode_utf8()
+            .is_ok_and(|segment| constraint.matches(&segment)),
+        None => true,
+    }
+}
+
+fn parses<T: FromStr>(segment: &str) -> bool {
+    segment.parse::<T>().is_ok()
+}
+
+// 形如 `67e55044-10b1-426f-9247-bb680e5fe0c8`
+fn is_uuid(segment: &str) -> bool {
+    let groups = segment.split('-').collect::<Vec<_>>();
+
+    groups.len() == 5
+        && groups
+            .iter()
+            .zip(&[8, 4, 4, 4, 12])
+            .all(|(group, len)| {
+                group.len() == *len && group.bytes().all(|b| b.is_ascii_hexdigit())
+            })
+}
@@ -1,5 +1,9 @@
 use super::*;
 
+use http::{header, Method};
+
+use super::trailing_slash::RedirectTo;
+
 
 // 创建空路由
 // Infallible
@@ -46,6 +50,13 @@ impl<E> fmt::Debug for EmptyRouter<E> {
     }
 }
 
+// 空路由不接受任何方法，作为方法链的末端
+impl<E> RouteMethods for EmptyRouter<E> {
+    fn methods(&self) -> Option<MethodFilter> {
+        Some(MethodFilter::empty())
+    }
+}
+
 // 为 EmptyRouter 实现 Service
 
 impl<B, E> Service<Request<B>> for EmptyRouter<E>
@@ -72,11 +83,42 @@ where
             self.status = StatusCode::METHOD_NOT_ALLOWED;
         }
 
+        if self.status == StatusCode::NOT_FOUND {
+            // 路径只差一个 `/`：重定向到路由的路径，不再交给外层路由器
+            if let Some(RedirectTo(location)) = request.extensions_mut().remove::<RedirectTo>() {
+                let mut res = Response::new(crate::body::empty());
+                *res.status_mut() = StatusCode::PERMANENT_REDIRECT;
+                res.headers_mut().insert(header::LOCATION, location);
+
+                return EmptyRouterFuture {
+                    future: ready(Ok(res)),
+                };
+            }
+        }
+
         let mut res = Response::new(crate::body::empty());
+        let mut status = self.status;
+
+        if status == StatusCode::METHOD_NOT_ALLOWED {
+            // 路径匹配但方法不匹配：通过 Allow 告诉客户端可用的方法，
+            // 没有显式处理的 OPTIONS 请求直接返回这些方法
+            let allowed = request
+                .extensions()
+                .get::<AllowedMethods>()
+                .map_or_else(MethodFilter::empty, |allowed| allowed.0)
+                | MethodFilter::OPTIONS;
+
+            if request.method() == Method::OPTIONS {
+                status = StatusCode::OK;
+            }
+
+            res.headers_mut()
+                .insert(header::ALLOW, allowed.to_header_value());
+        }
 
         res.extensions_mut().insert(FromEmptyRouter { request });
 
-        *res.status_mut() = self.status;
+        *res.status_mut() = status;
         EmptyRouterFuture {
             future: ready(Ok(res)),
         }
@@ -86,6 +128,130 @@ where
 #[derive(Clone, Copy)]
 struct NoMethodMatch;
 
+// 请求经过的所有 `OnMethod` 能够处理的方法
+#[derive(Clone, Copy)]
+struct AllowedMethods(MethodFilter);
+
+/// Record that the route the request matched accepts `method`, used for the
+/// `Allow` header when no method matches.
+pub(crate) fn allow_methods<B>(request: &mut Request<B>, method: MethodFilter) {
+    let extensions = request.extensions_mut();
+
+    if let Some(allowed) = extensions.get_mut::<AllowedMethods>() {
+        allowed.0 |= method;
+    } else {
+        extensions.insert(AllowedMethods(method));
+    }
+}
+
+/// Record that the request path only matched routes that don't accept its
+/// method, so it gets a `405 Method Not Allowed` response if nothing else
+/// handles it.
+pub(crate) fn reject_method<B>(request: &mut Request<B>, allowed: MethodFilter) {
+    allow_methods(request, allowed);
+    request.extensions_mut().insert(NoMethodMatch);
+}
+
 pub struct FromEmptyRouter<B> {
     pub request: Request<B>,
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        handler::{get, on},
+        http::{Method, StatusCode},
+        router::{MethodFilter, TrailingSlash},
+        test_client::TestClient,
+        Router,
+    };
+
+    #[tokio::test]
+    async fn method_not_allowed_lists_allowed_methods() {
+        let client = TestClient::new(
+            Router::new()
+                .route("/users", get(|| async { "list" }).post(|| async { "create" }))
+                .route("/teams", on(MethodFilter::PUT, || async { "update" })),
+        );
+
+        client
+            .request(Method::DELETE, "/users")
+            .send()
+            .await
+            .assert_status(StatusCode::METHOD_NOT_ALLOWED)
+            .assert_header("allow", "GET,HEAD,POST,OPTIONS")
+            .assert_text("");
+
+        client
+            .get("/teams")
+            .send()
+            .await
+            .assert_status(StatusCode::METHOD_NOT_ALLOWED)
+            .assert_header("allow", "PUT,OPTIONS");
+
+        let res = client.get("/missing").send().await;
+        res.assert_status(StatusCode::NOT_FOUND).assert_text("");
+        assert!(res.header("allow").is_none());
+    }
+
+    #[tokio::test]
+    async fn options() {
+        let client = TestClient::new(
+            Router::new()
+                .route("/users", get(|| async { "list" }))
+                .route("/teams", on(MethodFilter::OPTIONS, || async { "options" })),
+        );
+
+        // 没有显式处理 OPTIONS 的路由直接返回可用的方法
+        client
+            .request(Method::OPTIONS, "/users")
+            .send()
+            .await
+            .assert_status(StatusCode::OK)
+            .assert_header("allow", "GET,HEAD,OPTIONS")
+            .assert_text("");
+
+        client
+            .request(Method::OPTIONS, "/teams")
+            .send()
+            .await
+            .assert_status(StatusCode::OK)
+            .assert_text("options");
+
+        let res = client.request(Method::OPTIONS, "/missing").send().await;
+        res.assert_status(StatusCode::NOT_FOUND);
+        assert!(res.header("allow").is_none());
+    }
+
+    #[tokio::test]
+    async fn redirects_trailing_slash() {
+        let client = TestClient::new(
+            Router::new()
+                .route("/users", get(|| async { "users" }))
+                .route("/teams/", get(|| async { "teams" }))
+                .trailing_slash(TrailingSlash::Redirect),
+        );
+
+        for (path, location) in [
+            ("/users/", "/users"),
+            ("/users/?page=2", "/users?page=2"),
+            ("/teams", "/teams/"),
+            ("//users", "/users"),
+        ] {
+            client
+                .get(path)
+                .send()
+                .await
+                .assert_status(StatusCode::PERMANENT_REDIRECT)
+                .assert_header("location", location)
+                .assert_text("");
+        }
+
+        client.get("/users").send().await.assert_text("users");
+        client
+            .get("/missing/")
+            .send()
+            .await
+            .assert_status(StatusCode::NOT_FOUND);
+    }
+}
@@ -17,7 +17,9 @@ use tower::{
 use tower_service::Service;
 
 use crate::BoxError;
-use crate::buffer::MpscBuffer;
+use crate::buffer::{BufferError, MpscBuffer};
+
+use super::route::{unnest, Held, MatchedRoutes, RouteId, Unnested};
 
 opaque_future! {
     /// Response future for [`EmptyRouter`](super::EmptyRouter).
@@ -32,7 +34,9 @@ opaque_future! {
 }
 
 pin_project! {
-    /// The response future for [`Route`](super::Route).
+    /// The response future for [`Host`](super::Host), also used by
+    /// [`MergeFuture`] to call the second router when the first one has no
+    /// route for the request.
     #[derive(Debug)]
     pub struct RouteFuture<S, F, B>
     where
@@ -54,6 +58,18 @@ where
             state: RouteFutureInner::A {
                 a,
                 fallback: Some(fallback),
+                unnested: None,
+            },
+        }
+    }
+
+    // 嵌套路由：回退之前先恢复被内部路由修改过的请求
+    pub(crate) fn nested(a: Oneshot<S, Request<B>>, fallback: F, unnested: Unnested) -> Self {
+        RouteFuture {
+            state: RouteFutureInner::A {
+                a,
+                fallback: Some(fallback),
+                unnested: Some(unnested),
             },
         }
     }
@@ -77,6 +93,7 @@ pin_project! {
             #[pin]
             a: Oneshot<S, Request<B>>,
             fallback: Option<F>,
+            unnested: Option<Unnested>,
         },
         B {
             #[pin]
@@ -99,10 +116,14 @@ where
             let mut this = self.as_mut().project();
 
             let new_state = match this.state.as_mut().project() {
-                RouteFutureInnerProj::A { a, fallback } => {
+                RouteFutureInnerProj::A {
+                    a,
+                    fallback,
+                    unnested,
+                } => {
                     let mut response = ready!(a.poll(cx))?;
 
-                    let req = if let Some(ext) =
+                    let mut req = if let Some(ext) =
                         response.extensions_mut().remove::<FromEmptyRouter<B>>()
                     {
                         ext.request
@@ -110,6 +131,10 @@ where
                         return Poll::Ready(Ok(response));
                     };
 
+                    if let Some(unnested) = unnested.take() {
+                        unnested.restore(&mut req);
+                    }
+
                     RouteFutureInner::B {
                         b: fallback
                             .take()
@@ -126,39 +151,383 @@ where
 }
 
 
-// pin_project! {
-//     /// The response future for [`BoxRoute`](super::BoxRoute).
-//     pub struct BoxRouteFuture<B, E>
-//     where
-//         E: Into<BoxError>,
-//     {
-//         #[pin]
-//         pub(super) inner: Oneshot<
-//             MpscBuffer<
-//                 BoxService<Request<B>, Response<BoxBody>, E >,
-//                 Request<B>
-//             >,
-//             Request<B>,
-//         >,
-//     }
-// }
-
-// impl<B, E> Future for BoxRouteFuture<B, E>
-// where
-//     E: Into<BoxError>,
-// {
-//     type Output = Result<Response<BoxBody>, E>;
-
-//     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-//         self.project().inner.poll(cx)
-//     }
-// }
-
-// impl<B, E> fmt::Debug for BoxRouteFuture<B, E>
-// where
-//     E: Into<BoxError>,
-// {
-//     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-//         f.debug_struct("BoxRouteFuture").finish()
-//     }
-// }
\ No newline at end of file
+pin_project! {
+    /// The response future for [`Route`](super::Route) and
+    /// [`Nested`](super::Nested).
+    pub struct DispatchFuture<S, F, B>
+    where
+        S: Service<Request<B>>,
+        F: Service<Request<B>>
+    {
+        #[pin]
+        state: DispatchFutureInner<S, F, B>,
+    }
+}
+
+impl<S, F, B> DispatchFuture<S, F, B>
+where
+    S: Service<Request<B>>,
+    F: Service<Request<B>>,
+{
+    pub(crate) fn called(
+        id: RouteId,
+        route: Oneshot<S, Request<B>>,
+        tail: Option<F>,
+        unnested: Option<Unnested>,
+    ) -> Self {
+        DispatchFuture {
+            state: DispatchFutureInner::Called {
+                route,
+                id,
+                tail,
+                unnested,
+            },
+        }
+    }
+
+    pub(crate) fn passed(fallback: F::Future, held: Option<Held<S, F>>) -> Self {
+        DispatchFuture {
+            state: DispatchFutureInner::Passed { fallback, held },
+        }
+    }
+}
+
+pin_project! {
+    #[project = DispatchFutureInnerProj]
+    enum DispatchFutureInner<S, F, B>
+    where
+        S: Service<Request<B>>,
+        F: Service<Request<B>>,
+    {
+        // 请求交给了链上更早的部分
+        Passed {
+            #[pin]
+            fallback: F::Future,
+            held: Option<Held<S, F>>,
+        },
+        // 调用了这个路由，它不处理请求时交给 `tail`
+        Called {
+            #[pin]
+            route: Oneshot<S, Request<B>>,
+            id: RouteId,
+            tail: Option<F>,
+            unnested: Option<Unnested>,
+        },
+        Continued {
+            #[pin]
+            tail: Oneshot<F, Request<B>>,
+        },
+    }
+}
+
+impl<S, F, B> Future for DispatchFuture<S, F, B>
+where
+    S: Service<Request<B>, Response = Response<BoxBody>>,
+    F: Service<Request<B>, Response = Response<BoxBody>, Error = S::Error>,
+    B: Send + Sync + 'static,
+{
+    type Output = Result<Response<BoxBody>, S::Error>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        loop {
+            let mut this = self.as_mut().project();
+
+            let new_state = match this.state.as_mut().project() {
+                DispatchFutureInnerProj::Passed { fallback, held } => {
+                    let mut response = ready!(fallback.poll(cx))?;
+
+                    // 优先级更高的路由都没有处理请求时才轮到留下的路由
+                    let is_next = held.as_ref().is_some_and(|held| {
+                        response
+                            .extensions()
+                            .get::<FromEmptyRouter<B>>()
+                            .is_some_and(|ext| {
+                                MatchedRoutes::current(&ext.request) == Some(held.id)
+                            })
+                    });
+                    if !is_next {
+                        return Poll::Ready(Ok(response));
+                    }
+
+                    let held = held.take().expect("checked above");
+                    let mut request = response
+                        .extensions_mut()
+                        .remove::<FromEmptyRouter<B>>()
+                        .expect("checked above")
+                        .request;
+
+                    let unnested = match &held.pattern {
+                        Some(pattern) => match unnest(pattern, &mut request) {
+                            Some(unnested) => Some(unnested),
+                            None => {
+                                MatchedRoutes::remove(&mut request, held.id);
+                                response
+                                    .extensions_mut()
+                                    .insert(FromEmptyRouter { request });
+                                return Poll::Ready(Ok(response));
+                            }
+                        },
+                        None => Some(Unnested::save(&request)),
+                    };
+
+                    DispatchFutureInner::Called {
+                        route: held.svc.oneshot(request),
+                        id: held.id,
+                        tail: held.tail,
+                        unnested,
+                    }
+                }
+                DispatchFutureInnerProj::Called {
+                    route,
+                    id,
+                    tail,
+                    unnested,
+                } => {
+                    let mut response = ready!(route.poll(cx))?;
+
+                    let mut request = match response.extensions_mut().remove::<FromEmptyRouter<B>>()
+                    {
+                        Some(ext) => ext.request,
+                        None => return Poll::Ready(Ok(response)),
+                    };
+
+                    if let Some(unnested) = unnested.take() {
+                        unnested.restore(&mut request);
+                    }
+                    MatchedRoutes::remove(&mut request, *id);
+
+                    match tail.take() {
+                        Some(tail) => DispatchFutureInner::Continued {
+                            tail: tail.oneshot(request),
+                        },
+                        // 之后没有别的路由要尝试，这个路由的响应就是最终的响应
+                        None => {
+                            response
+                                .extensions_mut()
+                                .insert(FromEmptyRouter { request });
+                            return Poll::Ready(Ok(response));
+                        }
+                    }
+                }
+                DispatchFutureInnerProj::Continued { tail } => return tail.poll(cx),
+            };
+
+            this.state.set(new_state);
+        }
+    }
+}
+
+impl<S, F, B> fmt::Debug for DispatchFuture<S, F, B>
+where
+    S: Service<Request<B>>,
+    F: Service<Request<B>>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DispatchFuture").finish()
+    }
+}
+
+pin_project! {
+    /// The response future for [`Fallback`](super::Fallback).
+    #[derive(Debug)]
+    pub struct FallbackFuture<S, T, B>
+    where
+        S: Service<Request<B>>,
+        T: Service<Request<B>>
+    {
+        #[pin]
+        state: FallbackFutureInner<S, T, B>,
+    }
+}
+
+impl<S, T, B> FallbackFuture<S, T, B>
+where
+    S: Service<Request<B>>,
+    T: Service<Request<B>>,
+{
+    pub(crate) fn new(inner: Oneshot<S, Request<B>>, fallback: T) -> Self {
+        FallbackFuture {
+            state: FallbackFutureInner::Inner {
+                inner,
+                fallback: Some(fallback),
+            },
+        }
+    }
+}
+
+pin_project! {
+    #[project = FallbackFutureInnerProj]
+    #[derive(Debug)]
+    enum FallbackFutureInner<S, T, B>
+    where
+        S: Service<Request<B>>,
+        T: Service<Request<B>>,
+    {
+        Inner {
+            #[pin]
+            inner: Oneshot<S, Request<B>>,
+            fallback: Option<T>,
+        },
+        Fallback {
+            #[pin]
+            fallback: Oneshot<T, Request<B>>
+        },
+    }
+}
+
+impl<S, T, B> Future for FallbackFuture<S, T, B>
+where
+    S: Service<Request<B>, Response = Response<BoxBody>>,
+    T: Service<Request<B>, Response = Response<BoxBody>, Error = S::Error>,
+    B: Send + Sync + 'static,
+{
+    type Output = Result<Response<BoxBody>, S::Error>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        loop {
+            let mut this = self.as_mut().project();
+
+            let new_state = match this.state.as_mut().project() {
+                FallbackFutureInnerProj::Inner { inner, fallback } => {
+                    let mut response = ready!(inner.poll(cx))?;
+
+                    // 不管是否回退都要去掉请求，避免被外层的路由当成没有匹配
+                    let from_empty_router = response.extensions_mut().remove::<FromEmptyRouter<B>>();
+
+                    // 只处理没有匹配到路由的请求，405 原样返回
+                    let req = match from_empty_router {
+                        // 还有匹配到的路由没有尝试过，交还给链上之后添加的路由
+                        Some(ext) if !MatchedRoutes::is_exhausted(&ext.request) => {
+                            response.extensions_mut().insert(ext);
+                            return Poll::Ready(Ok(response));
+                        }
+                        Some(ext) if response.status() == StatusCode::NOT_FOUND => ext.request,
+                        _ => return Poll::Ready(Ok(response)),
+                    };
+
+                    FallbackFutureInner::Fallback {
+                        fallback: fallback
+                            .take()
+                            .expect("future polled after completion")
+                            .oneshot(req),
+                    }
+                }
+                FallbackFutureInnerProj::Fallback { fallback } => return fallback.poll(cx),
+            };
+
+            this.state.set(new_state);
+        }
+    }
+}
+
+pin_project! {
+    /// The response future for [`Merge`](super::Merge).
+    #[derive(Debug)]
+    pub struct MergeFuture<S, T, B>
+    where
+        S: Service<Request<B>>,
+        T: Service<Request<B>>
+    {
+        #[pin]
+        state: MergeFutureInner<S, T, B>,
+    }
+}
+
+impl<S, T, B> MergeFuture<S, T, B>
+where
+    S: Service<Request<B>>,
+    T: Service<Request<B>>,
+{
+    pub(crate) fn forward(inner: RouteFuture<S, T, B>) -> Self {
+        MergeFuture {
+            state: MergeFutureInner::Forward { inner },
+        }
+    }
+
+    pub(crate) fn reversed(inner: RouteFuture<T, S, B>) -> Self {
+        MergeFuture {
+            state: MergeFutureInner::Reversed { inner },
+        }
+    }
+}
+
+pin_project! {
+    #[project = MergeFutureInnerProj]
+    #[derive(Debug)]
+    enum MergeFutureInner<S, T, B>
+    where
+        S: Service<Request<B>>,
+        T: Service<Request<B>>,
+    {
+        Forward {
+            #[pin]
+            inner: RouteFuture<S, T, B>,
+        },
+        Reversed {
+            #[pin]
+            inner: RouteFuture<T, S, B>,
+        },
+    }
+}
+
+impl<S, T, B> Future for MergeFuture<S, T, B>
+where
+    S: Service<Request<B>, Response = Response<BoxBody>>,
+    T: Service<Request<B>, Response = Response<BoxBody>, Error = S::Error>,
+    B: Send + Sync + 'static,
+{
+    type Output = Result<Response<BoxBody>, S::Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.project().state.project() {
+            MergeFutureInnerProj::Forward { inner } => inner.poll(cx),
+            MergeFutureInnerProj::Reversed { inner } => inner.poll(cx),
+        }
+    }
+}
+
+pin_project! {
+    /// The response future for [`BoxRoute`](super::BoxRoute).
+    pub struct BoxRouteFuture<B, E>
+    where
+        E: Into<BoxError>,
+    {
+        #[pin]
+        pub(super) inner: Oneshot<
+            MpscBuffer<
+                BoxService<Request<B>, Response<BoxBody>, E >,
+                Request<B>
+            >,
+            Request<B>,
+        >,
+    }
+}
+
+impl<B, E> Future for BoxRouteFuture<B, E>
+where
+    E: Into<BoxError>,
+{
+    type Output = Result<Response<BoxBody>, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match ready!(self.project().inner.poll(cx)) {
+            Ok(res) => Poll::Ready(Ok(res)),
+            Err(BufferError::Service(err)) => Poll::Ready(Err(err)),
+            // 缓冲区已满或者后台任务已经退出
+            Err(BufferError::Overloaded) | Err(BufferError::Closed) => {
+                let mut res = Response::new(crate::body::empty());
+                *res.status_mut() = StatusCode::SERVICE_UNAVAILABLE;
+                Poll::Ready(Ok(res))
+            }
+        }
+    }
+}
+
+impl<B, E> fmt::Debug for BoxRouteFuture<B, E>
+where
+    E: Into<BoxError>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BoxRouteFuture").finish()
+    }
+}
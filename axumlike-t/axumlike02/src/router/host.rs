@@ -0,0 +1,398 @@
+// 虚拟主机：按照请求的 Host（HTTP/2 中是 `:authority`）分发到不同的服务
+//
+// 和 Router 一样，HostRouter 在 `call` 中统一匹配，把匹配到的 id 写入
+// MatchedRoutes，链上的每个 `Host` 只需要比较 id。
+use super::*;
+
+use super::route::{insert_url_params, MatchedRoutes, Unnested};
+
+use http::header;
+
+/// Routes requests to different services based on their host.
+///
+/// The host is taken from the request URI, which holds the `:authority` of
+/// HTTP/2 requests, or from the `Host` header otherwise. Ports are ignored
+/// and hosts are compared case-insensitively.
+///
+/// ```rust,no_run
+/// use axumlike02::{
+///     handler::get,
+///     router::{HostRouter, Router},
+/// };
+///
+/// let api = Router::new().route("/", get(|| async { "api" }));
+/// let tenants = Router::new().route("/", get(|| async { "tenant" }));
+///
+/// let app = HostRouter::new()
+///     .host("api.example.com", api)
+///     .host("{tenant}.example.com", tenants);
+/// # async {
+/// # axumlike02::Server::bind(&"".parse().unwrap()).serve(app.into_make_service()).await.unwrap();
+/// # };
+/// ```
+///
+/// A `{name}` label matches any single label of the host, and its value is
+/// added to the URL params like the captures of a path. When several hosts
+/// match, the one with the most literal labels is used, so `api.example.com`
+/// is preferred over `{tenant}.example.com` regardless of the order they were
+/// added in.
+///
+/// Requests whose host doesn't match, or whose service doesn't have a route
+/// for them, get an empty `404 Not Found` response.
+#[derive(Debug, Clone)]
+pub struct HostRouter<S> {
+    svc: S,
+    hosts: Arc<Vec<(HostPattern, RouteId)>>,
+}
+
+impl<E> HostRouter<EmptyRouter<E>> {
+    /// Create a new `HostRouter`.
+    ///
+    /// Unless you add additional hosts this will respond to `404 Not Found` to
+    /// all requests.
+    pub fn new() -> Self {
+        Self {
+            svc: EmptyRouter::not_found(),
+            hosts: Arc::new(Vec::new()),
+        }
+    }
+}
+
+impl<E> Default for HostRouter<EmptyRouter<E>> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S> HostRouter<S> {
+    /// Send requests whose host matches `pattern` to `svc`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `pattern` is empty, contains an empty label, or contains a
+    /// `{}` capture without a name.
+    pub fn host<T>(mut self, pattern: &str, svc: T) -> HostRouter<Host<T, S>> {
+        let id = RouteId::next();
+        Arc::make_mut(&mut self.hosts).push((HostPattern::new(pattern), id));
+
+        HostRouter {
+            svc: Host {
+                id,
+                svc,
+                fallback: self.svc,
+            },
+            hosts: self.hosts,
+        }
+    }
+
+    /// Convert this host router into a [`MakeService`], that is a [`Service`]
+    /// whose response is another service.
+    ///
+    /// [`MakeService`]: tower::make::MakeService
+    pub fn into_make_service(self) -> IntoMakeService<Self>
+    where
+        S: Clone,
+    {
+        IntoMakeService::new(self)
+    }
+}
+
+impl<S> RouteMethods for HostRouter<S> {}
+
+impl<S, B> Service<Request<B>> for HostRouter<S>
+where
+    S: Service<Request<B>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.svc.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: Request<B>) -> Self::Future {
+        let matched = request_host(&req).and_then(|host| find_host(&self.hosts, host));
+
+        match matched {
+            Some(HostMatch { id, captures, .. }) => {
+                req.extensions_mut().insert(MatchedRoutes(vec![id]));
+                if !captures.is_empty() {
+                    insert_url_params(&mut req, captures);
+                }
+            }
+            None => {
+                req.extensions_mut().insert(MatchedRoutes(Vec::new()));
+            }
+        }
+
+        self.svc.call(req)
+    }
+}
+
+struct HostMatch {
+    id: RouteId,
+    captures: Vec<(String, String)>,
+    literals: usize,
+}
+
+// 字面量标签更多的优先，相同时先添加的优先
+fn find_host(hosts: &[(HostPattern, RouteId)], host: &str) -> Option<HostMatch> {
+    let mut best: Option<HostMatch> = None;
+
+    for (pattern, id) in hosts {
+        if matches!(&best, Some(best) if best.literals >= pattern.literals) {
+            continue;
+        }
+
+        if let Some(captures) = pattern.matches(host) {
+            best = Some(HostMatch {
+                id: *id,
+                captures,
+                literals: pattern.literals,
+            });
+        }
+    }
+
+    best
+}
+
+/// A [`Service`] called for requests to some host.
+///
+/// Created with [`HostRouter::host`]. See that method for more details.
+#[derive(Debug, Clone)]
+pub struct Host<S, F> {
+    id: RouteId,
+    svc: S,
+    fallback: F,
+}
+
+impl<S, F, B> Service<Request<B>> for Host<S, F>
+where
+    S: Service<Request<B>, Response = Response<BoxBody>> + Clone,
+    F: Service<Request<B>, Response = Response<BoxBody>, Error = S::Error> + Clone,
+    B: Send + Sync + 'static,
+{
+    type Response = Response<BoxBody>;
+    type Error = S::Error;
+    type Future = RouteFuture<S, F, B>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: Request<B>) -> Self::Future {
+        let matched = req
+            .extensions()
+            .get::<MatchedRoutes>()
+            .is_some_and(|matched| matched.0.contains(&self.id));
+
+        if matched {
+            // 内部的路由器会修改请求，回退之前需要恢复
+            let unnested = Unnested::save(&req);
+            let fut = self.svc.clone().oneshot(req);
+            RouteFuture::nested(fut, self.fallback.clone(), unnested)
+        } else {
+            let fut = self.fallback.clone().oneshot(req);
+            RouteFuture::b(fut)
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct HostPattern {
+    labels: Box<[Label]>,
+    // 字面量标签的数量，用来决定匹配的优先级
+    literals: usize,
+}
+
+#[derive(Debug, Clone)]
+enum Label {
+    Literal(String),
+    Capture(String),
+}
+
+impl HostPattern {
+    fn new(pattern: &str) -> Self {
+        assert!(!pattern.is_empty(), "Host pattern cannot be empty");
+
+        let labels = pattern
+            .split('.')
+            .map(|label| {
+                assert!(
+                    !label.is_empty(),
+                    "Invalid host `{}`: labels cannot be empty",
+                    pattern
+                );
+
+                match label.strip_prefix('{').and_then(|l| l.strip_suffix('}')) {
+                    Some(name) => {
+                        assert!(
+                            !name.is_empty(),
+                            "Invalid host `{}`: captures must have a name",
+                            pattern
+                        );
+                        Label::Capture(name.to_owned())
+                    }
+                    None => Label::Literal(label.to_ascii_lowercase()),
+                }
+            })
+            .collect::<Box<[_]>>();
+
+        let literals = labels
+            .iter()
+            .filter(|label| matches!(label, Label::Literal(_)))
+            .count();
+
+        Self { labels, literals }
+    }
+
+    fn matches(&self, host: &str) -> Option<Vec<(String, String)>> {
+        let mut parts = host.split('.');
+        let mut captures = Vec::new();
+
+        for label in self.labels.iter() {
+            let part = parts.next()?;
+
+            match label {
+                Label::Literal(literal) if literal.eq_ignore_ascii_case(part) => {}
+                Label::Capture(name) if !part.is_empty() => {
+                    captures.push((name.clone(), part.to_ascii_lowercase()));
+                }
+                _ => return None,
+            }
+        }
+
+        if parts.next().is_some() {
+            return None;
+        }
+
+        Some(captures)
+    }
+}
+
+/// The host of the request without its port or a trailing dot.
+fn request_host<B>(req: &Request<B>) -> Option<&str> {
+    let host = match req.uri().host() {
+        Some(host) => host,
+        None => {
+            let host = req.headers().get(header::HOST)?.to_str().ok()?;
+
+            // IPv6 地址本身包含 `:`，例如 `[::1]:3000`
+            if host.starts_with('[') {
+                &host[..=host.find(']')?]
+            } else {
+                host.split(':').next()?
+            }
+        }
+    };
+
+    Some(host.strip_suffix('.').unwrap_or(host))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{body::Body, extract::Path, handler::get, test_client::TestClient};
+
+    fn app() -> HostRouter<
+        impl Service<Request<Body>, Response = Response<BoxBody>, Error = Infallible> + Clone,
+    > {
+        let tenants = Router::new()
+            .route("/", get(|Path(tenant): Path<String>| async { tenant }))
+            .route(
+                "/users/:id",
+                get(|Path((tenant, id)): Path<(String, u32)>| async move {
+                    format!("{} {}", tenant, id)
+                }),
+            );
+        let api = Router::new().route("/", get(|| async { "api" }));
+
+        // 有通配标签的先添加，字面量更多的仍然优先
+        HostRouter::new()
+            .host("{tenant}.example.com", tenants)
+            .host("api.example.com", api)
+    }
+
+    #[tokio::test]
+    async fn captures_labels() {
+        let client = TestClient::new(app());
+
+        client
+            .get("/")
+            .header("host", "acme.example.com")
+            .send()
+            .await
+            .assert_status(StatusCode::OK)
+            .assert_text("acme");
+
+        // 主机的参数在路径的参数之前
+        client
+            .get("/users/7")
+            .header("host", "acme.example.com")
+            .send()
+            .await
+            .assert_text("acme 7");
+    }
+
+    #[tokio::test]
+    async fn prefers_literal_labels() {
+        let client = TestClient::new(app());
+
+        client
+            .get("/")
+            .header("host", "api.example.com")
+            .send()
+            .await
+            .assert_text("api");
+    }
+
+    #[tokio::test]
+    async fn ignores_case_ports_and_trailing_dot() {
+        let client = TestClient::new(app());
+
+        for host in ["API.Example.COM", "api.example.com:8080", "api.example.com."] {
+            client.get("/").header("host", host).send().await.assert_text("api");
+        }
+
+        client
+            .get("/")
+            .header("host", "ACME.example.com:3000")
+            .send()
+            .await
+            .assert_text("acme");
+
+        // HTTP/2 的 `:authority` 在 URI 里
+        client
+            .get("http://api.example.com:8080/")
+            .send()
+            .await
+            .assert_text("api");
+    }
+
+    #[tokio::test]
+    async fn falls_through_to_not_found() {
+        let client = TestClient::new(app());
+
+        for host in ["example.com", "a.b.example.com", "api.example.org", "[::1]:3000"] {
+            client
+                .get("/")
+                .header("host", host)
+                .send()
+                .await
+                .assert_status(StatusCode::NOT_FOUND);
+        }
+
+        client.get("/").send().await.assert_status(StatusCode::NOT_FOUND);
+
+        // 匹配到了主机，但是没有这个路径
+        client
+            .get("/missing")
+            .header("host", "api.example.com")
+            .send()
+            .await
+            .assert_status(StatusCode::NOT_FOUND)
+            .assert_text("");
+    }
+}
@@ -1,5 +1,5 @@
 use bitflags::bitflags;
-use http::Method;
+use http::{HeaderValue, Method};
 
 bitflags! {
     /// A filter that matches one or more HTTP methods.
@@ -42,4 +42,87 @@ impl MethodFilter {
         };
         self.contains(method)
     }
-}
\ No newline at end of file
+
+    /// The methods that a route accepting `self` handles itself, without the
+    /// `HEAD` implied by `GET`.
+    ///
+    /// Routes only conflict on these, so an explicit `HEAD` route and a `GET`
+    /// route can be added in either order, and the `HEAD` route takes over
+    /// the `HEAD` requests the `GET` route would otherwise get.
+    pub(crate) fn explicit(self) -> Self {
+        if self.contains(Self::GET) {
+            self - Self::HEAD
+        } else {
+            self
+        }
+    }
+
+    /// Render the filter as the value of an `Allow` header, such as
+    /// `GET,HEAD,POST`.
+    pub(crate) fn to_header_value(self) -> HeaderValue {
+        let value = self.names().collect::<Vec<_>>().join(",");
+
+        HeaderValue::from_str(&value).expect("method names are valid header values")
+    }
+
+    pub(crate) fn names(self) -> impl Iterator<Item = &'static str> {
+        const METHODS: [(MethodFilter, &str); 9] = [
+            (MethodFilter::GET, "GET"),
+            (MethodFilter::HEAD, "HEAD"),
+            (MethodFilter::POST, "POST"),
+            (MethodFilter::PUT, "PUT"),
+            (MethodFilter::PATCH, "PATCH"),
+            (MethodFilter::DELETE, "DELETE"),
+            (MethodFilter::OPTIONS, "OPTIONS"),
+            (MethodFilter::CONNECT, "CONNECT"),
+            (MethodFilter::TRACE, "TRACE"),
+        ];
+
+        METHODS
+            .iter()
+            .filter(move |(method, _)| self.contains(*method))
+            .map(|(_, name)| *name)
+    }
+}
+
+/// The HTTP methods a service accepts, used by
+/// [`Router::route`](super::Router::route) to merge routes added for the same
+/// path and to reject the ones that would shadow each other.
+///
+/// The method helpers, such as [`handler::get`](crate::handler::get) or
+/// [`service::post`](crate::service::post), implement it with the methods of
+/// the whole chain. Other services can use the default implementation, which
+/// means the service may accept any method and isn't checked:
+///
+/// ```rust
+/// use axumlike02::router::RouteMethods;
+///
+/// #[derive(Clone)]
+/// struct MyService;
+///
+/// impl RouteMethods for MyService {}
+/// ```
+pub trait RouteMethods {
+    /// The methods accepted by the service, or `None` if it may accept any
+    /// method.
+    fn methods(&self) -> Option<MethodFilter> {
+        None
+    }
+}
+
+// tower 自带的服务不知道自己接受哪些方法
+impl<T> RouteMethods for tower::util::ServiceFn<T> {}
+
+impl<S, F> RouteMethods for tower::util::MapRequest<S, F> {}
+
+impl<S, F> RouteMethods for tower::util::MapResponse<S, F> {}
+
+impl<S, F> RouteMethods for tower::util::MapErr<S, F> {}
+
+impl<T, U, E> RouteMethods for tower::util::BoxService<T, U, E> {}
+
+impl<T, R> RouteMethods for tower::buffer::Buffer<T, R>
+where
+    T: tower_service::Service<R>,
+{
+}
@@ -0,0 +1,389 @@
+// 命名路由：根据路由名称和参数反向生成 URL
+use super::route::{PathPattern, Segment};
+
+use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
+use std::{collections::HashMap, error::Error as StdError, fmt, sync::Arc};
+
+// 路径段中需要编码的字符，参考 https://url.spec.whatwg.org/#path-percent-encode-set
+const PATH: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'`')
+    .add(b'{')
+    .add(b'}')
+    .add(b'%');
+
+// `:param` 只能匹配一个路径段，所以还要编码 `/`
+const SEGMENT: &AsciiSet = &PATH.add(b'/');
+
+/// The routes of a router that were added with a name.
+///
+/// Every router inserts its `NamedRoutes` into the request extensions, so
+/// handlers can use it to build links to other routes instead of hard-coding
+/// their paths:
+///
+/// ```rust,no_run
+/// use axumlike02::{
+///     handler::get,
+///     http::Request,
+///     router::NamedRoutes,
+///     Router,
+/// };
+/// use hyper::Body;
+///
+/// async fn create_user(req: Request<Body>) -> String {
+///     let routes = req.extensions().get::<NamedRoutes>().unwrap();
+///     routes.url_for("user_detail", &[("id", "42")]).unwrap()
+/// }
+///
+/// let app = Router::new()
+///     .named_route("user_detail", "/users/:id", get(|| async {}))
+///     .route("/users", get(create_user));
+/// # async {
+/// # axumlike02::Server::bind(&"".parse().unwrap()).serve(app.into_make_service()).await.unwrap();
+/// # };
+/// ```
+///
+/// Routers nested with [`Router::nest`](super::Router::nest) see the names
+/// of their own routes, including the prefix they are nested at, and the
+/// names of the routers they are nested in.
+#[derive(Debug, Clone, Default)]
+pub struct NamedRoutes {
+    routes: Arc<HashMap<String, PathPattern>>,
+    // 嵌套的路由器生成的路径需要加上前缀
+    prefix: String,
+    // 外层路由器的命名路由，找不到名称时继续往外找
+    parent: Option<Arc<NamedRoutes>>,
+}
+
+impl NamedRoutes {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.routes.is_empty()
+    }
+
+    /// The named routes of a router nested at `prefix` inside the router that
+    /// `parent` belongs to.
+    pub(crate) fn nested(&self, prefix: &str, parent: NamedRoutes) -> Self {
+        Self {
+            routes: self.routes.clone(),
+            prefix: prefix.to_owned(),
+            parent: Some(Arc::new(parent)),
+        }
+    }
+
+    pub(crate) fn get(&self, name: &str) -> Option<&PathPattern> {
+        self.routes.get(name)
+    }
+
+    pub(crate) fn insert(&mut self, name: String, pattern: PathPattern) {
+        Arc::make_mut(&mut self.routes).insert(name, pattern);
+    }
+
+    /// Build the path of the route called `name`, filling in its captures
+    /// with `params`.
+    ///
+    /// The values are percent-encoded. A `*name` catch-all may contain `/`,
+    /// which is kept as is. Optional captures may be left out, as long as the
+    /// captures after them are left out too.
+    ///
+    /// # Errors
+    ///
+    /// Fails if there is no route called `name`, if a capture of the route is
+    /// missing from `params`, if a value doesn't satisfy the constraint of
+    /// its capture, or if `params` contains a name that isn't a capture of
+    /// the route.
+    pub fn url_for(&self, name: &str, params: &[(&str, &str)]) -> Result<String, UrlForError> {
+        let pattern = match (self.get(name), &self.parent) {
+            (Some(pattern), _) => pattern,
+            (None, Some(parent)) => return parent.url_for(name, params),
+            (None, None) => {
+                return Err(UrlForError::UnknownRoute {
+                    route: name.to_owned(),
+                })
+            }
+        };
+
+        let lookup = |key: &str| {
+            params
+                .iter()
+                .find(|(param, _)| *param == key)
+                .map(|(_, value)| *value)
+        };
+
+        let mut url = self.prefix.clone();
+        // 第一个没有提供的可选参数，后面的参数也不能再提供
+        let mut omitted = None;
+
+        for (idx, segment) in pattern.segments().iter().enumerate() {
+            let (key, constraint, value, set) = match segment {
+                Segment::Static(part) => {
+                    url.push('/');
+                    url.push_str(part);
+                    continue;
+                }
+                Segment::Param(key, constraint) => (key, constraint.as_ref(), lookup(key), SEGMENT),
+                Segment::CatchAll(key) => (key, None, lookup(key), PATH),
+            };
+
+            match value {
+                Some(value) => {
+                    if let Some(omitted) = omitted {
+                        return Err(UrlForError::MissingParam {
+                            route: name.to_owned(),
+                            param: omitted,
+                        });
+                    }
+
+                    // 生成的路径必须能匹配回这个路由
+                    if let Some(constraint) = constraint {
+                        if !constraint.matches(value) {
+                            return Err(UrlForError::InvalidParam {
+                                route: name.to_owned(),
+                                param: key.clone(),
+                                value: value.to_owned(),
+                                constraint: constraint.to_string(),
+                            });
+                        }
+                    }
+
+                    url.push('/');
+                    url.extend(utf8_percent_encode(value, set));
+                }
+                None if idx >= pattern.required_len() => {
+                    omitted.get_or_insert_with(|| key.clone());
+                }
+                None => {
+                    return Err(UrlForError::MissingParam {
+                        route: name.to_owned(),
+                        param: key.clone(),
+                    })
+                }
+            }
+        }
+
+        let is_capture = |key: &str| {
+            pattern.segments().iter().any(|segment| match segment {
+                Segment::Param(param, _) | Segment::CatchAll(param) => param == key,
+                Segment::Static(_) => false,
+            })
+        };
+
+        if let Some((param, _)) = params.iter().find(|(param, _)| !is_capture(param)) {
+            return Err(UrlForError::UnexpectedParam {
+                route: name.to_owned(),
+                param: (*param).to_owned(),
+            });
+        }
+
+        if url.is_empty() {
+            url.push('/');
+        }
+
+        Ok(url)
+    }
+}
+
+/// Error returned by [`NamedRoutes::url_for`].
+#[derive(Debug)]
+pub enum UrlForError {
+    /// No route was added with this name.
+    UnknownRoute {
+        /// The name of the route.
+        route: String,
+    },
+    /// A capture of the route wasn't given a value.
+    MissingParam {
+        /// The name of the route.
+        route: String,
+        /// The name of the capture.
+        param: String,
+    },
+    /// The value of a capture doesn't satisfy the capture's constraint.
+    InvalidParam {
+        /// The name of the route.
+        route: String,
+        /// The name of the capture.
+        param: String,
+        /// The value that was given.
+        value: String,
+        /// The constraint, such as `<u32>`.
+        constraint: String,
+    },
+    /// A value was given for a capture the route doesn't have.
+    UnexpectedParam {
+        /// The name of the route.
+        route: String,
+        /// The name of the capture.
+        param: String,
+    },
+}
+
+impl fmt::Display for UrlForError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownRoute { route } => write!(f, "No route named `{}`", route),
+            Self::MissingParam { route, param } => {
+                write!(f, "Missing param `{}` for route `{}`", param, route)
+            }
+            Self::InvalidParam {
+                route,
+                param,
+                value,
+                constraint,
+            } => write!(
+                f,
+                "Value `{}` for param `{}` of route `{}` doesn't match `{}`",
+                value, param, route, constraint
+            ),
+            Self::UnexpectedParam { route, param } => {
+                write!(f, "Route `{}` has no param `{}`", route, param)
+            }
+        }
+    }
+}
+
+impl StdError for UrlForError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        body::{Body, BoxBody},
+        extract::Extension,
+        handler::get,
+        http::{Request, Response, StatusCode},
+        test_client::TestClient,
+        Router,
+    };
+    use std::{convert::Infallible, sync::Mutex};
+    use tower_service::Service;
+
+    type Slot = Arc<Mutex<Option<NamedRoutes>>>;
+
+    fn app(
+        slot: &Slot,
+    ) -> Router<impl Service<Request<Body>, Response = Response<BoxBody>, Error = Infallible> + Clone>
+    {
+        // 处理请求时把 `NamedRoutes` 存下来，测试里再调用 `url_for`
+        let capture = {
+            let slot = slot.clone();
+            move |Extension(routes): Extension<NamedRoutes>| {
+                *slot.lock().unwrap() = Some(routes);
+                async {}
+            }
+        };
+
+        let api = Router::new().named_route("item", "/items/:id", get(capture.clone()));
+
+        Router::new()
+            .named_route("home", "/", get(capture.clone()))
+            .named_route("user", "/users/:id<u32>", get(capture.clone()))
+            .named_route("search", "/search/:query", get(capture.clone()))
+            .named_route("files", "/files/*path", get(capture.clone()))
+            .named_route("pages", "/pages/:section?/:page?", get(capture))
+            .nest("/api/:version", api)
+    }
+
+    async fn named_routes(path: &str) -> NamedRoutes {
+        let slot = Slot::default();
+        let client = TestClient::new(app(&slot));
+        client.get(path).send().await.assert_status(StatusCode::OK);
+
+        let routes = slot.lock().unwrap().take();
+        routes.unwrap()
+    }
+
+    #[tokio::test]
+    async fn builds_paths() {
+        let routes = named_routes("/").await;
+
+        assert_eq!(routes.url_for("home", &[]).unwrap(), "/");
+        assert_eq!(
+            routes.url_for("user", &[("id", "42")]).unwrap(),
+            "/users/42"
+        );
+        assert_eq!(routes.url_for("pages", &[]).unwrap(), "/pages");
+        assert_eq!(
+            routes.url_for("pages", &[("section", "docs")]).unwrap(),
+            "/pages/docs"
+        );
+        assert_eq!(
+            routes
+                .url_for("pages", &[("page", "2"), ("section", "docs")])
+                .unwrap(),
+            "/pages/docs/2"
+        );
+    }
+
+    #[tokio::test]
+    async fn nested_routers_add_their_prefix() {
+        let routes = named_routes("/api/v1/items/1").await;
+
+        assert_eq!(
+            routes.url_for("item", &[("id", "7")]).unwrap(),
+            "/api/v1/items/7"
+        );
+        // 外层路由器的路由不加前缀
+        assert_eq!(routes.url_for("user", &[("id", "7")]).unwrap(), "/users/7");
+        assert_eq!(routes.url_for("home", &[]).unwrap(), "/");
+    }
+
+    #[tokio::test]
+    async fn percent_encodes_values() {
+        let routes = named_routes("/").await;
+
+        // `:param` 只有一段，`/` 也要编码
+        assert_eq!(
+            routes.url_for("search", &[("query", "a b/c?%")]).unwrap(),
+            "/search/a%20b%2Fc%3F%25"
+        );
+        // `*catch_all` 保留 `/`
+        assert_eq!(
+            routes
+                .url_for("files", &[("path", "docs/a b.txt")])
+                .unwrap(),
+            "/files/docs/a%20b.txt"
+        );
+        assert_eq!(
+            routes.url_for("search", &[("query", "jürgen")]).unwrap(),
+            "/search/j%C3%BCrgen"
+        );
+    }
+
+    #[tokio::test]
+    async fn errors() {
+        let routes = named_routes("/").await;
+
+        let err = routes.url_for("missing", &[]).unwrap_err();
+        assert!(matches!(&err, UrlForError::UnknownRoute { route } if route == "missing"));
+        assert_eq!(err.to_string(), "No route named `missing`");
+
+        let err = routes.url_for("user", &[]).unwrap_err();
+        assert!(matches!(&err, UrlForError::MissingParam { param, .. } if param == "id"));
+        assert_eq!(err.to_string(), "Missing param `id` for route `user`");
+
+        // 后面的可选参数提供了，前面的就不能省略
+        let err = routes.url_for("pages", &[("page", "2")]).unwrap_err();
+        assert!(matches!(&err, UrlForError::MissingParam { param, .. } if param == "section"));
+
+        let err = routes
+            .url_for("user", &[("id", "1"), ("name", "bob")])
+            .unwrap_err();
+        assert!(matches!(&err, UrlForError::UnexpectedParam { param, .. } if param == "name"));
+        assert_eq!(err.to_string(), "Route `user` has no param `name`");
+
+        let err = routes.url_for("user", &[("id", "abc")]).unwrap_err();
+        assert!(matches!(
+            &err,
+            UrlForError::InvalidParam { param, value, .. } if param == "id" && value == "abc"
+        ));
+        assert_eq!(
+            err.to_string(),
+            "Value `abc` for param `id` of route `user` doesn't match `<u32>`"
+        );
+    }
+}
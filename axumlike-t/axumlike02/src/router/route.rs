@@ -1,26 +1,143 @@
-// 路由机制：使用 正则表达式
+// 路由机制：由 Router 在前缀树上统一匹配，请求沿着 `Route`/`Nested` 链向下，
+// 每一层只需要和当前要调用的路由 id 比较
 use super::*;
 
-use bytes::Bytes;
-use regex::Regex;
+use http::Method;
+use std::sync::atomic::{AtomicU32, Ordering};
+
 use crate::util::ByteStr;
 
+use super::{
+    constraint::{param_matches, Constraint},
+    empty_router::reject_method,
+    future::DispatchFuture,
+    tree::Node,
+    NamedRoutes,
+};
 
 #[derive(Debug, Clone)]
 pub(crate) struct PathPattern(Arc<Inner>);
 
 #[derive(Debug)]
 struct Inner {
-    full_path_regex: Regex,
-    capture_group_names: Box<[Bytes]>,
+    path: Box<str>,
+    segments: Box<[Segment]>,
+    // 可选段之前的段数，没有可选段时等于 `segments.len()`
+    required: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Segment {
+    Static(String),
+    Param(String, Option<Constraint>),
+    CatchAll(String),
+}
+
+/// Identifies a single call to [`Router::route`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) struct RouteId(u32);
+
+impl RouteId {
+    pub(crate) fn next() -> Self {
+        static ID: AtomicU32 = AtomicU32::new(0);
+        Self(ID.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+/// A call to [`Router::route`] or [`Router::nest`], kept so the routes can be
+/// added to the tree of another router by [`Router::merge`].
+#[derive(Debug, Clone)]
+pub(crate) struct Registration {
+    pub(crate) id: RouteId,
+    pub(crate) pattern: PathPattern,
+    pub(crate) kind: RouteKind,
+    pub(crate) name: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum RouteKind {
+    // 普通路由，以及它接受的方法，不知道时为 `None`
+    Route(Option<MethodFilter>),
+    // 嵌套路由，匹配前缀下的任意路径和方法
+    Nested,
+}
+
+/// A route registered on a [`Router`].
+///
+/// Returned by [`Router::routes`].
+#[derive(Debug, Clone, Copy)]
+pub struct RouteInfo<'a> {
+    registration: &'a Registration,
+}
+
+impl<'a> RouteInfo<'a> {
+    /// The path the route was added with, such as `/users/:id`.
+    pub fn path(&self) -> &'a str {
+        self.registration.pattern.as_str()
+    }
+
+    /// The methods accepted by the route.
+    ///
+    /// Nested services, and services that weren't built with the method
+    /// helpers such as [`handler::get`](crate::handler::get), accept any
+    /// method.
+    pub fn methods(&self) -> MethodFilter {
+        match self.registration.kind {
+            RouteKind::Route(methods) => methods.unwrap_or_else(MethodFilter::all),
+            RouteKind::Nested => MethodFilter::all(),
+        }
+    }
+
+    /// The name the route was added with, if any.
+    ///
+    /// See [`Router::named_route`] for more details.
+    pub fn name(&self) -> Option<&'a str> {
+        self.registration.name.as_deref()
+    }
+
+    /// Whether the route was added with [`Router::nest`], in which case
+    /// [`RouteInfo::path`] is the prefix it was nested at.
+    pub fn is_nested(&self) -> bool {
+        matches!(self.registration.kind, RouteKind::Nested)
+    }
+}
+
+/// An iterator over the routes of a [`Router`].
+///
+/// Created with [`Router::routes`]. See that method for more details.
+#[derive(Debug, Clone)]
+pub struct Routes<'a> {
+    pub(crate) inner: std::slice::Iter<'a, Registration>,
+}
+
+impl<'a> Iterator for Routes<'a> {
+    type Item = RouteInfo<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner
+            .next()
+            .map(|registration| RouteInfo { registration })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
 }
 
+impl ExactSizeIterator for Routes<'_> {}
+
 // 路由匹配： 基于 service
 #[derive(Debug, Clone)]
 pub struct Route<S, F> {
-    pub(crate) pattern: PathPattern,
+    pub(crate) id: RouteId,
     pub(crate) svc: S,      // service
     pub(crate) fallback: F, // 回退机制，比如回退到 404，或者支持 SPA应用
+    // 知道接受哪些方法的路由只在接受请求的方法时被选中，一定会处理请求
+    pub(crate) may_decline: bool,
+    // 链上更早的部分里有 `Fallback`
+    pub(crate) fallback_below: bool,
+    // 路径里有参数，省略了可选参数时也要让 `Path` 知道参数是空的
+    pub(crate) captures: bool,
 }
 
 // B 代表 Body
@@ -32,28 +149,374 @@ where
 {
     type Response = Response<BoxBody>;
     type Error = S::Error;
-    type Future = RouteFuture<S, F, B>;
+    type Future = DispatchFuture<S, F, B>;
+
+    // 不是这个路由的请求直接交给 `fallback`，所以它也要准备好
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.fallback.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: Request<B>) -> Self::Future {
+        let Self {
+            id,
+            svc,
+            fallback,
+            may_decline,
+            fallback_below,
+            captures,
+        } = self;
+
+        if MatchedRoutes::current(&req) == Some(*id) {
+            let tail = continuation(&req, *may_decline, *fallback_below, fallback);
+            // 服务本身可能是路由器，交还请求之前会改掉匹配结果
+            let saved = may_decline.then(|| Unnested::save(&req));
+            if *captures && req.extensions().get::<Option<UrlParams>>().is_none() {
+                req.extensions_mut().insert(Some(UrlParams(Vec::new())));
+            }
+            DispatchFuture::called(*id, svc.clone().oneshot(req), tail, saved)
+        } else if MatchedRoutes::contains(&req, *id) {
+            // 优先级更高的路由在链上更早的部分，它们都没有处理请求时再调用这个路由
+            let held = Held {
+                id: *id,
+                svc: svc.clone(),
+                pattern: None,
+                tail: may_decline.then(|| fallback.clone()),
+            };
+            DispatchFuture::passed(fallback.call(req), Some(held))
+        } else {
+            DispatchFuture::passed(fallback.call(req), None)
+        }
+    }
+}
+
+/// A [`Service`] mounted under a path prefix.
+///
+/// Created with [`Router::nest`]. See that method for more details.
+#[derive(Debug, Clone)]
+pub struct Nested<S, F> {
+    pub(crate) id: RouteId,
+    pub(crate) pattern: PathPattern,
+    pub(crate) svc: S,
+    pub(crate) fallback: F,
+    pub(crate) fallback_below: bool,
+}
+
+impl<S, F, B> Service<Request<B>> for Nested<S, F>
+where
+    S: Service<Request<B>, Response = Response<BoxBody>> + Clone,
+    F: Service<Request<B>, Response = Response<BoxBody>, Error = S::Error> + Clone,
+    B: Send + Sync + 'static,
+{
+    type Response = Response<BoxBody>;
+    type Error = S::Error;
+    type Future = DispatchFuture<S, F, B>;
 
-    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
-        Poll::Ready(Ok(()))
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.fallback.poll_ready(cx)
     }
 
     fn call(&mut self, mut req: Request<B>) -> Self::Future {
-        if let Some(captures) = self.pattern.full_match(&req) {
-            insert_url_params(&mut req, captures);
-            let fut = self.svc.clone().oneshot(req);
-            RouteFuture::a(fut, self.fallback.clone())
+        let Self {
+            id,
+            pattern,
+            svc,
+            fallback,
+            fallback_below,
+        } = self;
+
+        if MatchedRoutes::current(&req) == Some(*id) {
+            // 嵌套的服务可能没有这个请求的路由
+            let tail = continuation(&req, true, *fallback_below, fallback);
+
+            match unnest(pattern, &mut req) {
+                Some(unnested) => {
+                    DispatchFuture::called(*id, svc.clone().oneshot(req), tail, Some(unnested))
+                }
+                None => {
+                    MatchedRoutes::remove(&mut req, *id);
+                    DispatchFuture::passed(fallback.call(req), None)
+                }
+            }
+        } else if MatchedRoutes::contains(&req, *id) {
+            let held = Held {
+                id: *id,
+                svc: svc.clone(),
+                pattern: Some(pattern.clone()),
+                tail: Some(fallback.clone()),
+            };
+            DispatchFuture::passed(fallback.call(req), Some(held))
         } else {
-            let fut = self.fallback.clone().oneshot(req);
-            RouteFuture::b(fut)
+            DispatchFuture::passed(fallback.call(req), None)
         }
     }
 }
 
+/// A route left for later because routes with a higher priority are further
+/// down the chain.
+pub(crate) struct Held<S, F> {
+    pub(crate) id: RouteId,
+    pub(crate) svc: S,
+    // 嵌套路由的前缀
+    pub(crate) pattern: Option<PathPattern>,
+    pub(crate) tail: Option<F>,
+}
+
+// 只有路由可能不处理请求，并且之后还有其他路由或者 `Fallback` 要尝试时，才需要克隆链上剩下的部分
+fn continuation<B, F>(
+    req: &Request<B>,
+    may_decline: bool,
+    fallback_below: bool,
+    fallback: &F,
+) -> Option<F>
+where
+    F: Clone,
+{
+    if may_decline && (fallback_below || MatchedRoutes::has_others(req)) {
+        Some(fallback.clone())
+    } else {
+        None
+    }
+}
+
+/// Strip the prefix matched by `pattern` from the request before it's passed
+/// to a nested service.
+///
+/// Returns what's needed to restore the request if the nested service hands
+/// it back, or `None` if the prefix doesn't match.
+pub(crate) fn unnest<B>(pattern: &PathPattern, req: &mut Request<B>) -> Option<Unnested> {
+    let prefix = pattern.prefix_match(req).map(|(prefix, _)| prefix.to_owned())?;
+
+    // 前缀里的参数已经由 Router 写入了 UrlParams，这里只需要去掉前缀
+    let unnested = Unnested::save(req);
+    let exact = req.uri().path().len() == prefix.len();
+    *req.uri_mut() = strip_prefix(req.uri(), prefix.len());
+
+    // 记录从最外层开始去掉的完整前缀
+    let full_prefix = match req.extensions().get::<NestedPrefix>() {
+        Some(outer) => format!("{}{}", outer.prefix, prefix),
+        None => prefix,
+    };
+    req.extensions_mut().insert(NestedPrefix {
+        prefix: full_prefix,
+        exact,
+    });
+
+    Some(unnested)
+}
+
+fn strip_prefix(uri: &Uri, prefix_len: usize) -> Uri {
+    let path = &uri.path()[prefix_len..];
+    let path = if path.is_empty() { "/" } else { path };
+
+    with_path(uri, path)
+}
+
+/// Replace the path of `uri`, keeping its query.
+pub(crate) fn with_path(uri: &Uri, path: &str) -> Uri {
+    let path_and_query = if let Some(query) = uri.query() {
+        format!("{}?{}", path, query)
+    } else {
+        path.to_owned()
+    };
+
+    let mut parts = uri.clone().into_parts();
+    parts.path_and_query = Some(
+        path_and_query
+            .parse()
+            .expect("replacing the path with a valid path is still valid"),
+    );
+
+    Uri::from_parts(parts).expect("only the path was changed so the uri is still valid")
+}
+
+/// The parts of a request that the inner service of a [`Nested`] changes,
+/// restored before handing the request back to the outer router.
 #[derive(Debug)]
+pub(crate) struct Unnested {
+    uri: Uri,
+    matched: Option<MatchedRoutes>,
+    params: Option<Option<UrlParams>>,
+    prefix: Option<NestedPrefix>,
+    named: Option<NamedRoutes>,
+}
+
+impl Unnested {
+    pub(crate) fn save<B>(req: &Request<B>) -> Self {
+        Self {
+            uri: req.uri().clone(),
+            matched: req.extensions().get::<MatchedRoutes>().cloned(),
+            params: req.extensions().get::<Option<UrlParams>>().cloned(),
+            prefix: req.extensions().get::<NestedPrefix>().cloned(),
+            named: req.extensions().get::<NamedRoutes>().cloned(),
+        }
+    }
+
+    pub(crate) fn restore<B>(self, req: &mut Request<B>) {
+        *req.uri_mut() = self.uri;
+
+        match self.matched {
+            Some(matched) => req.extensions_mut().insert(matched),
+            None => req.extensions_mut().remove::<MatchedRoutes>(),
+        };
+
+        match self.params {
+            Some(params) => req.extensions_mut().insert(params),
+            None => req.extensions_mut().remove::<Option<UrlParams>>(),
+        };
+
+        match self.prefix {
+            Some(prefix) => req.extensions_mut().insert(prefix),
+            None => req.extensions_mut().remove::<NestedPrefix>(),
+        };
+
+        match self.named {
+            Some(named) => req.extensions_mut().insert(named),
+            None => req.extensions_mut().remove::<NamedRoutes>(),
+        };
+    }
+}
+
+/// The routes whose pattern matched the request path and that haven't been
+/// tried yet, in the order they are tried, recorded by [`Router`] before the
+/// request reaches its routes.
+#[derive(Debug, Clone)]
+pub(crate) struct MatchedRoutes(pub(crate) Vec<RouteId>);
+
+impl MatchedRoutes {
+    /// The route that should handle the request next.
+    pub(crate) fn current<B>(req: &Request<B>) -> Option<RouteId> {
+        req.extensions()
+            .get::<Self>()
+            .and_then(|matched| matched.0.first().copied())
+    }
+
+    /// Whether `id` is one of the routes left to try.
+    pub(crate) fn contains<B>(req: &Request<B>, id: RouteId) -> bool {
+        req.extensions()
+            .get::<Self>()
+            .is_some_and(|matched| matched.0.contains(&id))
+    }
+
+    /// Whether some route other than the current one is left to try.
+    pub(crate) fn has_others<B>(req: &Request<B>) -> bool {
+        req.extensions()
+            .get::<Self>()
+            .is_some_and(|matched| matched.0.len() > 1)
+    }
+
+    /// Whether every matched route has been tried.
+    pub(crate) fn is_exhausted<B>(req: &Request<B>) -> bool {
+        req.extensions()
+            .get::<Self>()
+            .is_none_or(|matched| matched.0.is_empty())
+    }
+
+    /// Record that the route `id` didn't handle the request.
+    pub(crate) fn remove<B>(req: &mut Request<B>, id: RouteId) {
+        if let Some(matched) = req.extensions_mut().get_mut::<Self>() {
+            matched.0.retain(|matched| *matched != id);
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
 pub(crate) struct UrlParams(pub(crate) Vec<(ByteStr, ByteStr)>);
 
-fn insert_url_params<B>(req: &mut Request<B>, params: Vec<(String, String)>) {
+/// The routes matching a request path, with the captured params copied out
+/// of the path so the request can still be modified.
+pub(crate) struct RouteMatch {
+    routes: Vec<RouteId>,
+    params: Vec<(String, String)>,
+    rejected: MethodFilter,
+    // 只匹配到了嵌套路由的前缀
+    pub(crate) nested: bool,
+}
+
+impl RouteMatch {
+    pub(crate) fn find(node: &Node, path: &str, method: &Method) -> Option<Self> {
+        let found = node.at(path, method)?;
+
+        Some(Self {
+            params: found
+                .params
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect(),
+            routes: found.routes,
+            rejected: found.rejected,
+            nested: found.nested,
+        })
+    }
+}
+
+/// Record the routes matched by the request path and the captured params in
+/// the request extensions.
+pub(crate) fn match_request<B>(req: &mut Request<B>, found: Option<RouteMatch>) {
+    let RouteMatch {
+        routes,
+        params,
+        rejected,
+        ..
+    } = found.unwrap_or(RouteMatch {
+        routes: Vec::new(),
+        params: Vec::new(),
+        rejected: MethodFilter::empty(),
+        nested: false,
+    });
+
+    // 不接受这个方法的路由不会被调用，由链的末端返回 405
+    if !rejected.is_empty() {
+        reject_method(req, rejected);
+    }
+
+    req.extensions_mut().insert(MatchedRoutes(routes));
+
+    if !params.is_empty() {
+        insert_url_params(req, params);
+    }
+}
+
+/// The part of the path stripped by [`Nested`] routes, from the outermost
+/// router down to the one handling the request.
+#[derive(Debug, Clone)]
+pub(crate) struct NestedPrefix {
+    pub(crate) prefix: String,
+    // 请求路径正好是前缀，去掉前缀之后的 `/` 是补上的
+    pub(crate) exact: bool,
+}
+
+impl NestedPrefix {
+    /// The request path before the prefixes were stripped, given the path
+    /// the nested service sees.
+    pub(crate) fn original_path(&self, path: &str) -> String {
+        if self.exact {
+            self.prefix.clone()
+        } else {
+            format!("{}{}", self.prefix, path)
+        }
+    }
+}
+
+/// Make the named routes of `named` available to handlers, together with the
+/// ones of the routers the request was nested in.
+pub(crate) fn insert_named_routes<B>(named: &NamedRoutes, req: &mut Request<B>) {
+    let named = match req.extensions().get::<NamedRoutes>() {
+        None => named.clone(),
+        // 没有命名路由的嵌套路由器直接使用外层的
+        Some(_) if named.is_empty() => return,
+        Some(parent) => {
+            let prefix = req
+                .extensions()
+                .get::<NestedPrefix>()
+                .map_or("", |prefix| &prefix.prefix);
+            named.nested(prefix, parent.clone())
+        }
+    };
+
+    req.extensions_mut().insert(named);
+}
+
+pub(crate) fn insert_url_params<B>(req: &mut Request<B>, params: Vec<(String, String)>) {
     let params = params
         .into_iter()
         .map(|(k, v)| (ByteStr::new(k), ByteStr::new(v)));
@@ -68,84 +531,148 @@ fn insert_url_params<B>(req: &mut Request<B>, params: Vec<(String, String)>) {
     }
 }
 
-
 impl PathPattern {
     pub(crate) fn new(pattern: &str) -> Self {
         assert!(pattern.starts_with('/'), "Route path must start with a `/`");
 
-        let mut capture_group_names = Vec::new();
+        // `/` 不包含任何路径段
+        let parts = if pattern == "/" {
+            Vec::new()
+        } else {
+            pattern[1..].split('/').collect::<Vec<_>>()
+        };
 
-        let pattern = pattern
-            .split('/')
-            .map(|part| {
-                if let Some(key) = part.strip_prefix(':') {
-                    capture_group_names.push(Bytes::copy_from_slice(key.as_bytes()));
+        let mut segments = Vec::with_capacity(parts.len());
+        let mut required = None;
 
-                    Cow::Owned(format!("(?P<{}>[^/]+)", key))
-                } else {
-                    Cow::Borrowed(part)
-                }
-            })
-            .collect::<Vec<_>>()
-            .join("/");
+        for (idx, part) in parts.iter().enumerate() {
+            let (part, optional) = match part.strip_suffix('?') {
+                Some(part) if part.starts_with(':') || part.starts_with('*') => (part, true),
+                _ => (*part, false),
+            };
+
+            let segment = if let Some(key) = part.strip_prefix(':') {
+                // `:name<constraint>`
+                let parsed = key.strip_suffix('>').and_then(|key| key.split_once('<'));
+                let (key, constraint) = match parsed {
+                    Some((key, constraint)) => {
+                        let constraint = Constraint::new(constraint).unwrap_or_else(|err| {
+                            panic!("Invalid constraint for capture `:{}`: {}", key, err)
+                        });
+                        (key, Some(constraint))
+                    }
+                    None => {
+                        assert!(
+                            !key.contains(['<', '>']),
+                            "Invalid constraint for capture `:{}`: expected `:name<constraint>`",
+                            key
+                        );
+                        (key, None)
+                    }
+                };
 
-        let full_path_regex =
-            Regex::new(&format!("^{}", pattern)).expect("invalid regex generated from route");
+                assert!(!key.is_empty(), "Route captures must have a name");
+                Segment::Param(key.to_owned(), constraint)
+            } else if let Some(key) = part.strip_prefix('*') {
+                assert!(!key.is_empty(), "Route captures must have a name");
+                assert!(
+                    idx + 1 == parts.len(),
+                    "Catch-all `*{}` must be the last segment of the route",
+                    key
+                );
+                Segment::CatchAll(key.to_owned())
+            } else {
+                Segment::Static(part.to_owned())
+            };
+
+            if optional {
+                required.get_or_insert(idx);
+            } else {
+                assert!(
+                    required.is_none(),
+                    "Only trailing segments of a route can be optional"
+                );
+            }
+
+            segments.push(segment);
+        }
 
         Self(Arc::new(Inner {
-            full_path_regex,
-            capture_group_names: capture_group_names.into(),
+            path: pattern.into(),
+            required: required.unwrap_or(segments.len()),
+            segments: segments.into(),
         }))
     }
 
-    pub(crate) fn full_match<B>(&self, req: &Request<B>) -> Option<Captures> {
-        self.do_match(req).and_then(|match_| {
-            if match_.full_match {
-                Some(match_.captures)
-            } else {
-                None
-            }
-        })
+    pub(crate) fn as_str(&self) -> &str {
+        &self.0.path
     }
 
-    pub(crate) fn prefix_match<'a, B>(&self, req: &'a Request<B>) -> Option<(&'a str, Captures)> {
-        self.do_match(req)
-            .map(|match_| (match_.matched, match_.captures))
+    pub(crate) fn segments(&self) -> &[Segment] {
+        &self.0.segments
     }
 
-    fn do_match<'a, B>(&self, req: &'a Request<B>) -> Option<Match<'a>> {
-        let path = req.uri().path();
+    /// The number of segments before the optional ones.
+    pub(crate) fn required_len(&self) -> usize {
+        self.0.required
+    }
 
-        self.0.full_path_regex.captures(path).map(|captures| {
-            let matched = captures.get(0).unwrap();
-            let full_match = matched.as_str() == path;
+    /// Every path this pattern stands for, from only the required segments up
+    /// to all the optional ones.
+    pub(crate) fn variants(&self) -> impl Iterator<Item = &[Segment]> {
+        (self.0.required..=self.0.segments.len()).map(move |len| &self.0.segments[..len])
+    }
 
-            let captures = self
-                .0
-                .capture_group_names
-                .iter()
-                .map(|bytes| {
-                    std::str::from_utf8(bytes)
-                        .expect("bytes were created from str so is valid utf-8")
-                })
-                .filter_map(|name| captures.name(name).map(|value| (name, value.as_str())))
-                .map(|(key, value)| (key.to_string(), value.to_string()))
-                .collect::<Vec<_>>();
+    /// Whether the pattern has any `:param` or `*catch_all` captures.
+    pub(crate) fn has_captures(&self) -> bool {
+        self.0
+            .segments
+            .iter()
+            .any(|segment| !matches!(segment, Segment::Static(_)))
+    }
 
-            Match {
-                captures,
-                full_match,
-                matched: matched.as_str(),
-            }
-        })
+    /// Whether the pattern ends in optional segments or a catch-all.
+    pub(crate) fn is_open_ended(&self) -> bool {
+        self.0.required < self.0.segments.len()
+            || matches!(self.0.segments.last(), Some(Segment::CatchAll(_)))
+    }
+
+    /// The segments used when nesting under this pattern, where `/api/` means
+    /// the same as `/api` and `/` matches everything.
+    pub(crate) fn prefix_segments(&self) -> &[Segment] {
+        match self.segments().split_last() {
+            Some((Segment::Static(last), rest)) if last.is_empty() => rest,
+            _ => self.segments(),
+        }
     }
-}
 
-struct Match<'a> {
-    captures: Captures,
-    // true if regex matched whole path, false if it only matched a prefix
-    full_match: bool,
-    matched: &'a str,
+    pub(crate) fn prefix_match<'a, B>(&self, req: &'a Request<B>) -> Option<(&'a str, Captures)> {
+        let path = req.uri().path();
+        let mut rest = Some(path.strip_prefix('/')?);
+        let mut matched_len = 0;
+        let mut captures = Vec::new();
+
+        for segment in self.prefix_segments() {
+            let remaining = rest?;
+            let (part, next) = match remaining.find('/') {
+                Some(idx) => (&remaining[..idx], Some(&remaining[idx + 1..])),
+                None => (remaining, None),
+            };
+
+            match segment {
+                Segment::Static(expected) if expected == part => {}
+                Segment::Param(name, constraint) if param_matches(constraint.as_ref(), part) => {
+                    captures.push((name.clone(), part.to_owned()));
+                }
+                _ => return None,
+            }
+
+            matched_len += 1 + part.len();
+            rest = next;
+        }
+
+        Some((&path[..matched_len], captures))
+    }
 }
 
 type Captures = Vec<(String, String)>;
@@ -0,0 +1,582 @@
+use super::empty_router::FromEmptyRouter;
+use crate::{
+    body::{box_body, Body, BoxBody},
+    handler::{get, Handler},
+    http::{Request, StatusCode},
+    test_client::TestClient,
+    router::{MethodFilter, RouteMethods, TrailingSlash},
+    service, Router,
+};
+use std::convert::Infallible;
+use tower::ServiceExt;
+
+async fn fallback() -> (StatusCode, &'static str) {
+    (StatusCode::NOT_FOUND, "fallback")
+}
+
+#[tokio::test]
+async fn nested_fallback_keeps_method_not_allowed() {
+    let api = Router::new()
+        .route("/users", get(|| async { "users" }))
+        .fallback(fallback.into_service());
+    let client = TestClient::new(Router::new().nest("/api", api));
+
+    client
+        .get("/api/users")
+        .send()
+        .await
+        .assert_status(StatusCode::OK)
+        .assert_text("users");
+
+    client
+        .get("/api/posts")
+        .send()
+        .await
+        .assert_status(StatusCode::NOT_FOUND)
+        .assert_text("fallback");
+
+    // 内部路由器的 405 不能被外层当成没有匹配
+    client
+        .request(http::Method::POST, "/api/users")
+        .send()
+        .await
+        .assert_status(StatusCode::METHOD_NOT_ALLOWED)
+        .assert_header("allow", "GET,HEAD,OPTIONS");
+}
+
+#[tokio::test]
+async fn fallback_strips_empty_router_request() {
+    let app = Router::new()
+        .route("/users", get(|| async { "users" }))
+        .fallback(fallback.into_service());
+
+    for method in ["GET", "POST"] {
+        let req = Request::builder()
+            .method(method)
+            .uri("/posts")
+            .body(Body::empty())
+            .unwrap();
+        let res = app.clone().oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+        assert!(res.extensions().get::<FromEmptyRouter<Body>>().is_none());
+
+        let req = Request::builder()
+            .method(method)
+            .uri("/users")
+            .body(Body::empty())
+            .unwrap();
+        let res = app.clone().oneshot(req).await.unwrap();
+        assert!(res.extensions().get::<FromEmptyRouter<Body>>().is_none());
+    }
+}
+
+#[tokio::test]
+async fn strict_collapses_duplicate_slashes() {
+    let app = Router::new().route("/users/:id", get(|| async { "user" }));
+    let client = TestClient::new(app);
+
+    client
+        .get("//users///1")
+        .send()
+        .await
+        .assert_status(StatusCode::OK)
+        .assert_text("user");
+
+    client
+        .get("/users/1/")
+        .send()
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn trailing_slash_policies() {
+    let users = || Router::new().route("/users", get(|| async { "users" }));
+
+    let client = TestClient::new(users().trailing_slash(TrailingSlash::Redirect));
+    client
+        .get("//users/?page=2")
+        .send()
+        .await
+        .assert_status(StatusCode::PERMANENT_REDIRECT)
+        .assert_header("location", "/users?page=2");
+
+    let client = TestClient::new(users().trailing_slash(TrailingSlash::Ignore));
+    client
+        .get("/users/")
+        .send()
+        .await
+        .assert_status(StatusCode::OK)
+        .assert_text("users");
+
+    // 嵌套的路由器重定向时加上外层的前缀
+    let client = TestClient::new(
+        Router::new()
+            .nest("/api", users())
+            .trailing_slash(TrailingSlash::Redirect),
+    );
+    for path in ["/api//users/", "//api//users", "/api/users/"] {
+        client
+            .get(path)
+            .send()
+            .await
+            .assert_status(StatusCode::PERMANENT_REDIRECT)
+            .assert_header("location", "/api/users");
+    }
+}
+
+#[tokio::test]
+async fn constraints_match_decoded_segments() {
+    let client = TestClient::new(
+        Router::new()
+            .route("/users/:id<u64>", get(|| async { "id" }))
+            .route("/users/:name", get(|| async { "name" })),
+    );
+
+    client.get("/users/%31%32").send().await.assert_text("id");
+    client.get("/users/12").send().await.assert_text("id");
+    client.get("/users/%31x").send().await.assert_text("name");
+}
+
+#[test]
+#[should_panic(expected = "Invalid constraint for capture `:id<u64`")]
+fn unclosed_constraint() {
+    TestClient::new(Router::new().route("/users/:id<u64", get(|| async {})));
+}
+
+#[tokio::test]
+async fn optional_catch_all_matches_empty_rest() {
+    let client = TestClient::new(
+        Router::new().route("/assets/*path?", get(|| async { "assets" })),
+    );
+
+    for path in ["/assets", "/assets/", "/assets/css/app.css"] {
+        client
+            .get(path)
+            .send()
+            .await
+            .assert_status(StatusCode::OK)
+            .assert_text("assets");
+    }
+
+    client.get("/assetsx").send().await.assert_status(StatusCode::NOT_FOUND);
+}
+
+// 把请求路径写进响应，分辨是哪个服务处理的
+fn echo(
+    tag: &'static str,
+) -> impl tower::Service<
+    Request<Body>,
+    Response = http::Response<BoxBody>,
+    Error = Infallible,
+    Future = impl Send,
+> + Clone
+       + RouteMethods {
+    tower::service_fn(move |req: Request<Body>| async move {
+        let res = http::Response::builder()
+            .header("x-route", tag)
+            .body(box_body(Body::from(req.uri().path().to_owned())))
+            .unwrap();
+        Ok(res)
+    })
+}
+
+#[tokio::test]
+async fn route_accepts_any_service() {
+    let stacked = tower::ServiceBuilder::new()
+        .map_request(|req: Request<Body>| req)
+        .service(echo("stacked"));
+    let boxed = Router::new()
+        .route("/boxed", get(|| async { "boxed" }))
+        .boxed();
+
+    let client = TestClient::new(
+        Router::new()
+            .route("/echo", echo("echo"))
+            .route("/handler", (|| async { "handler" }).into_service())
+            .route("/stacked", stacked)
+            .route("/boxed", boxed),
+    );
+
+    client.get("/echo").send().await.assert_text("/echo");
+    client
+        .request(http::Method::DELETE, "/echo")
+        .send()
+        .await
+        .assert_status(StatusCode::OK);
+    client.get("/handler").send().await.assert_text("handler");
+    client.get("/stacked").send().await.assert_text("/stacked");
+    client.get("/boxed").send().await.assert_text("boxed");
+}
+
+#[tokio::test]
+async fn head_before_get_is_not_a_conflict() {
+    let client = TestClient::new(
+        Router::new()
+            .route("/", service::head(echo("head")))
+            .route("/", service::get(echo("get")))
+            .route("/other", service::get(echo("get")))
+            .route("/other", service::head(echo("head"))),
+    );
+
+    for path in ["/", "/other"] {
+        client.get(path).send().await.assert_header("x-route", "get");
+        client
+            .request(http::Method::HEAD, path)
+            .send()
+            .await
+            .assert_header("x-route", "head");
+    }
+}
+
+#[test]
+#[should_panic(expected = "another route for the same path already handles `GET`")]
+fn duplicate_get_conflicts() {
+    TestClient::new(
+        Router::new()
+            .route("/", get(|| async {}))
+            .route("/", get(|| async {})),
+    );
+}
+
+#[tokio::test]
+async fn nest_handles_methods_the_deeper_route_does_not() {
+    let api = Router::new().route("/users", service::post(echo("nested")));
+    let client = TestClient::new(
+        Router::new()
+            .route("/api/users", service::get(echo("outer")))
+            .nest("/api", api),
+    );
+
+    client.get("/api/users").send().await.assert_header("x-route", "outer");
+    client
+        .post("/api/users")
+        .send()
+        .await
+        .assert_header("x-route", "nested");
+    client
+        .request(http::Method::DELETE, "/api/users")
+        .send()
+        .await
+        .assert_status(StatusCode::METHOD_NOT_ALLOWED)
+        .assert_header("allow", "GET,HEAD,POST,OPTIONS");
+}
+
+#[tokio::test]
+async fn segment_priority() {
+    let client = TestClient::new(
+        Router::new()
+            .route("/files/*path", get(|| async { "catch-all" }))
+            .route("/files/:name", get(|| async { "param" }))
+            .route("/files/:id<u64>", get(|| async { "constrained" }))
+            .route("/files/new", get(|| async { "static" })),
+    );
+
+    for (path, route) in [
+        ("/files/new", "static"),
+        ("/files/42", "constrained"),
+        ("/files/readme", "param"),
+        ("/files/docs/readme", "catch-all"),
+    ] {
+        client.get(path).send().await.assert_text(route);
+    }
+}
+
+#[tokio::test]
+async fn backtracks_when_deeper_segments_do_not_match() {
+    let client = TestClient::new(
+        Router::new()
+            .route("/users/new", get(|| async { "new" }))
+            .route("/users/:id/posts", get(|| async { "posts" }))
+            .route("/items/:id<u64>/edit", get(|| async { "edit" }))
+            .route("/items/:slug/view", get(|| async { "view" }))
+            .route("/docs/:page", get(|| async { "page" }))
+            .route("/docs/*rest", get(|| async { "rest" })),
+    );
+
+    for (path, route) in [
+        ("/users/new", "new"),
+        ("/users/new/posts", "posts"),
+        ("/items/5/edit", "edit"),
+        ("/items/5/view", "view"),
+        ("/docs/intro", "page"),
+        ("/docs/intro/setup", "rest"),
+    ] {
+        client.get(path).send().await.assert_text(route);
+    }
+
+    client
+        .get("/items/x/edit")
+        .send()
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn dispatches_around_layers() {
+    let client = TestClient::new(
+        Router::new()
+            .route("/inner", get(|| async { "inner" }))
+            .layer(tower::layer::util::Identity::new())
+            .route("/outer", get(|| async { "outer" }))
+            .route("/inner", crate::handler::on(MethodFilter::POST, || async { "post" })),
+    );
+
+    client.get("/inner").send().await.assert_text("inner");
+    client.post("/inner").send().await.assert_text("post");
+    client.get("/outer").send().await.assert_text("outer");
+    client
+        .request(http::Method::DELETE, "/inner")
+        .send()
+        .await
+        .assert_status(StatusCode::METHOD_NOT_ALLOWED)
+        .assert_header("allow", "GET,HEAD,POST,OPTIONS");
+}
+
+#[tokio::test]
+async fn tries_the_next_matched_route_when_one_declines() {
+    // 路由器作为服务时没有 `/x` 的路由，交还请求后轮到后添加的服务
+    let declines = Router::new().route("/other", get(|| async { "other" }));
+    let client = TestClient::new(
+        Router::new()
+            .route("/x", declines)
+            .route("/x", echo("second")),
+    );
+
+    client.get("/x").send().await.assert_header("x-route", "second");
+}
+
+#[tokio::test]
+async fn fallback_added_before_a_nest() {
+    let api = Router::new().route("/users", get(|| async { "users" }));
+    let client = TestClient::new(
+        Router::new()
+            .fallback(fallback.into_service())
+            .nest("/api", api)
+            .route("/", get(|| async { "root" })),
+    );
+
+    client.get("/api/users").send().await.assert_text("users");
+    client.get("/").send().await.assert_text("root");
+    client
+        .get("/api/missing")
+        .send()
+        .await
+        .assert_status(StatusCode::NOT_FOUND)
+        .assert_text("fallback");
+}
+
+#[tokio::test]
+async fn merge_combines_routes_and_methods() {
+    let users = Router::new().route("/users", get(|| async { "list" }));
+    let admin = Router::new()
+        .route("/users", crate::handler::on(MethodFilter::POST, || async { "create" }))
+        .route("/admin", get(|| async { "admin" }));
+    let client = TestClient::new(users.merge(admin));
+
+    client.get("/users").send().await.assert_text("list");
+    client.post("/users").send().await.assert_text("create");
+    client.get("/admin").send().await.assert_text("admin");
+
+    client
+        .request(http::Method::DELETE, "/users")
+        .send()
+        .await
+        .assert_status(StatusCode::METHOD_NOT_ALLOWED)
+        .assert_header("allow", "GET,HEAD,POST,OPTIONS");
+}
+
+#[tokio::test]
+async fn merge_calls_the_fallback_last() {
+    let with_fallback = || {
+        Router::new()
+            .route("/a", get(|| async { "a" }))
+            .fallback(fallback.into_service())
+    };
+    let without_fallback = || Router::new().route("/b", get(|| async { "b" }));
+
+    // 不管 fallback 在哪个路由器上，两个路由器的路由都优先
+    let clients = [
+        TestClient::new(with_fallback().merge(without_fallback()).boxed()),
+        TestClient::new(without_fallback().merge(with_fallback()).boxed()),
+    ];
+
+    for client in &clients {
+        client.get("/a").send().await.assert_text("a");
+        client.get("/b").send().await.assert_text("b");
+        client
+            .get("/c")
+            .send()
+            .await
+            .assert_status(StatusCode::NOT_FOUND)
+            .assert_text("fallback");
+    }
+}
+
+#[test]
+#[should_panic(expected = "Cannot merge two routers that both have a fallback")]
+fn merge_two_fallbacks() {
+    let a = Router::new()
+        .route("/a", get(|| async { "a" }))
+        .fallback(fallback.into_service());
+    let b = Router::new()
+        .route("/b", get(|| async { "b" }))
+        .fallback(fallback.into_service());
+    TestClient::new(a.merge(b));
+}
+
+#[tokio::test]
+async fn merge_keeps_either_trailing_slash_policy() {
+    let a = Router::new().route("/a", get(|| async { "a" }));
+    let b = Router::new()
+        .route("/b", get(|| async { "b" }))
+        .trailing_slash(TrailingSlash::Redirect);
+    let client = TestClient::new(a.merge(b));
+
+    for (path, location) in [("/a/", "/a"), ("/b/", "/b")] {
+        client
+            .get(path)
+            .send()
+            .await
+            .assert_status(StatusCode::PERMANENT_REDIRECT)
+            .assert_header("location", location);
+    }
+}
+
+#[test]
+#[should_panic(expected = "Cannot merge routers with different trailing slash policies")]
+fn merge_conflicting_trailing_slash_policies() {
+    let a = Router::new()
+        .route("/a", get(|| async { "a" }))
+        .trailing_slash(TrailingSlash::Ignore);
+    let b = Router::new()
+        .route("/b", get(|| async { "b" }))
+        .trailing_slash(TrailingSlash::Redirect);
+    TestClient::new(a.merge(b));
+}
+
+#[tokio::test]
+async fn routes_lists_registrations_in_order() {
+    let api = Router::new()
+        .route("/users", get(|| async {}))
+        .route("/teams", get(|| async {}));
+    let other = Router::new().route("/merged", service::post(echo("merged")));
+
+    let app = Router::new()
+        .route("/", get(|| async {}))
+        .named_route("user", "/users/:id", get(|| async {}).post(|| async {}))
+        .nest("/api", api)
+        .route("/any", echo("any"))
+        .merge(other);
+
+    let routes = app
+        .routes()
+        .map(|route| {
+            (
+                route.path(),
+                route.methods(),
+                route.name(),
+                route.is_nested(),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        routes,
+        vec![
+            ("/", MethodFilter::GET | MethodFilter::HEAD, None, false),
+            (
+                "/users/:id",
+                MethodFilter::GET | MethodFilter::HEAD | MethodFilter::POST,
+                Some("user"),
+                false,
+            ),
+            // 嵌套的路由器只列出前缀，不列出它自己的路由
+            ("/api", MethodFilter::all(), None, true),
+            ("/any", MethodFilter::all(), None, false),
+            ("/merged", MethodFilter::POST, None, false),
+        ]
+    );
+    assert_eq!(app.routes().len(), 5);
+
+    TestClient::new(app)
+        .get("/api/teams")
+        .send()
+        .await
+        .assert_status(StatusCode::OK);
+}
+
+async fn user(crate::extract::Path(id): crate::extract::Path<u32>) -> String {
+    format!("user {}", id)
+}
+
+// 类型擦除后的路由器可以作为返回值和结构体字段
+fn boxed_api() -> Router<super::BoxRoute> {
+    Router::new()
+        .route("/users/:id", get(user))
+        .fallback(fallback.into_service())
+        .boxed()
+}
+
+struct App {
+    router: Router<super::BoxRoute>,
+}
+
+#[tokio::test]
+async fn boxed_router_behaves_like_the_original() {
+    let app = App {
+        router: Router::new()
+            .route("/", get(|| async { "root" }))
+            .nest("/api", boxed_api())
+            .boxed(),
+    };
+
+    assert_eq!(
+        app.router
+            .routes()
+            .map(|route| route.path())
+            .collect::<Vec<_>>(),
+        vec!["/", "/api"]
+    );
+
+    let client = TestClient::new(app.router.clone());
+
+    client.get("/").send().await.assert_text("root");
+    client
+        .get("/api/users/1")
+        .send()
+        .await
+        .assert_status(StatusCode::OK)
+        .assert_text("user 1");
+    client
+        .get("/api/users/one")
+        .send()
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+    client
+        .post("/api/users/1")
+        .send()
+        .await
+        .assert_status(StatusCode::METHOD_NOT_ALLOWED)
+        .assert_header("allow", "GET,HEAD,OPTIONS");
+    client
+        .get("/api/teams")
+        .send()
+        .await
+        .assert_status(StatusCode::NOT_FOUND)
+        .assert_text("fallback");
+    client
+        .get("/teams")
+        .send()
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+
+    // 克隆共享同一个后台任务，并发请求都能得到响应
+    let responses = futures_util::future::join_all(
+        (0..32).map(|id| client.get(&format!("/api/users/{}", id)).send()),
+    )
+    .await;
+    for (id, res) in responses.iter().enumerate() {
+        res.assert_text(&format!("user {}", id));
+    }
+}
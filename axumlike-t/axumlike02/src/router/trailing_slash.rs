@@ -0,0 +1,141 @@
+// 路径规范化：合并重复的 `/`，并按照策略处理末尾的 `/`
+use super::{
+    route::{with_path, NestedPrefix, RouteMatch},
+    tree::Node,
+};
+
+use http::{HeaderValue, Request};
+use std::borrow::Cow;
+
+/// How a [`Router`](super::Router) handles paths that only differ from a
+/// route by a trailing slash or by duplicate slashes.
+///
+/// Set with [`Router::trailing_slash`](super::Router::trailing_slash). See
+/// that method for more details.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TrailingSlash {
+    /// Paths must match a route exactly apart from duplicate slashes, so
+    /// `//users` matches `/users` but `/users/` doesn't.
+    #[default]
+    Strict,
+    /// Redirect to the path of the route with `308 Permanent Redirect`.
+    Redirect,
+    /// Call the route as if the request was sent to its path.
+    Ignore,
+}
+
+/// The location the request should be redirected to instead of responding
+/// with `404 Not Found`.
+#[derive(Debug, Clone)]
+pub(crate) struct RedirectTo(pub(crate) HeaderValue);
+
+// 外层的路由器合并了重复的 `/`，内层的路由器即使路径已经匹配也需要重定向
+#[derive(Debug, Clone, Copy)]
+struct SlashesCollapsed;
+
+/// What [`normalize`] did with the request.
+pub(crate) enum Normalized {
+    /// The request should be redirected instead of being matched against the
+    /// routes.
+    Redirect,
+    /// The routes matching the request path, which may have been rewritten.
+    Route(Option<RouteMatch>),
+}
+
+/// Find the path matching a route in `node` that the request path only differs
+/// from by slashes, then apply `policy` to it.
+///
+/// The lookup done to find that path is returned, so the request doesn't have
+/// to be matched again.
+pub(crate) fn normalize<B>(node: &Node, policy: TrailingSlash, req: &mut Request<B>) -> Normalized {
+    let collapsed_outside = req.extensions_mut().remove::<SlashesCollapsed>().is_some();
+    let path = req.uri().path();
+    let collapsed = collapse_slashes(path);
+
+    // 严格模式只合并重复的 `/`，不会添加或去掉末尾的 `/`
+    if policy == TrailingSlash::Strict && matches!(collapsed, Cow::Borrowed(_)) {
+        return Normalized::Route(RouteMatch::find(node, path, req.method()));
+    }
+
+    let (canonical, found) = match RouteMatch::find(node, &collapsed, req.method()) {
+        // 末尾的 `/` 交给嵌套的路由器处理，这里只合并重复的 `/`
+        Some(found) if found.nested => {
+            if collapsed != path {
+                *req.uri_mut() = with_path(req.uri(), &collapsed);
+                if policy == TrailingSlash::Redirect {
+                    req.extensions_mut().insert(SlashesCollapsed);
+                }
+            }
+            return Normalized::Route(Some(found));
+        }
+        Some(found) => (collapsed.into_owned(), found),
+        None if policy == TrailingSlash::Strict => return Normalized::Route(None),
+        None => {
+            let found = toggle_trailing_slash(&collapsed).and_then(|toggled| {
+                RouteMatch::find(node, &toggled, req.method()).map(|found| (toggled, found))
+            });
+
+            match found {
+                Some(found) => found,
+                None => return Normalized::Route(None),
+            }
+        }
+    };
+
+    if canonical == path && !collapsed_outside {
+        return Normalized::Route(Some(found));
+    }
+
+    match policy {
+        TrailingSlash::Redirect => {
+            // 嵌套的路由器需要加上外层去掉的前缀
+            let prefix = req
+                .extensions()
+                .get::<NestedPrefix>()
+                .map_or("", |prefix| &prefix.prefix);
+
+            let location = match req.uri().query() {
+                Some(query) => format!("{}{}?{}", prefix, canonical, query),
+                None => format!("{}{}", prefix, canonical),
+            };
+
+            match HeaderValue::from_str(&location) {
+                Ok(location) => {
+                    req.extensions_mut().insert(RedirectTo(location));
+                    Normalized::Redirect
+                }
+                Err(_) => Normalized::Route(Some(found)),
+            }
+        }
+        TrailingSlash::Ignore | TrailingSlash::Strict => {
+            *req.uri_mut() = with_path(req.uri(), &canonical);
+            Normalized::Route(Some(found))
+        }
+    }
+}
+
+fn collapse_slashes(path: &str) -> Cow<'_, str> {
+    if !path.contains("//") {
+        return Cow::Borrowed(path);
+    }
+
+    let mut collapsed = String::with_capacity(path.len());
+    for c in path.chars() {
+        if c != '/' || !collapsed.ends_with('/') {
+            collapsed.push(c);
+        }
+    }
+
+    Cow::Owned(collapsed)
+}
+
+// `/` 没有另一种形式
+fn toggle_trailing_slash(path: &str) -> Option<String> {
+    if path == "/" {
+        None
+    } else if let Some(path) = path.strip_suffix('/') {
+        Some(path.to_owned())
+    } else {
+        Some(format!("{}/", path))
+    }
+}
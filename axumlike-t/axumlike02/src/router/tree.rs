@@ -0,0 +1,390 @@
+// 路由前缀树：按路径段（segment）组织所有路由
+//
+// 每个节点下有静态子节点、`:param` 子节点和一个 `*catch_all`，
+// 匹配时沿着路径逐段向下走，整个过程只和路径长度有关，和注册了多少路由无关。
+// 优先级：静态段 > 有约束的 `:param<..>` > `:param` > `*catch_all` > 嵌套路由的前缀，
+// 更具体的分支匹配失败时会回溯。匹配到的路由都不接受请求的方法时，再试外层的嵌套路由。
+use super::{
+    constraint::{param_matches, Constraint},
+    route::{PathPattern, RouteId, Segment},
+    MethodFilter,
+};
+
+use http::Method;
+use std::{collections::HashMap, fmt};
+
+#[derive(Debug, Clone)]
+pub(crate) struct Node {
+    statics: HashMap<String, Node>,
+    // 有约束的参数在前，按添加顺序尝试，没有约束的参数最多一个，放在最后
+    params: Vec<ParamNode>,
+    catch_all: Option<Box<CatchAll>>,
+    // 在这个节点结束的路由，同一路径可以注册多次（比如不同的 HTTP 方法）
+    routes: Vec<Endpoint>,
+    // 这些路由接受的方法（不包括 `GET` 隐含的 `HEAD`），用来发现同一路径上重复注册的方法
+    methods: MethodFilter,
+    // 以这个节点为前缀的嵌套路由，匹配剩余的任意路径
+    nested: Vec<RouteId>,
+}
+
+impl Default for Node {
+    fn default() -> Self {
+        Self {
+            statics: HashMap::new(),
+            params: Vec::new(),
+            catch_all: None,
+            routes: Vec::new(),
+            methods: MethodFilter::empty(),
+            nested: Vec::new(),
+        }
+    }
+}
+
+// 一个路由，以及它接受的方法，不知道时为 `None`
+#[derive(Debug, Clone, Copy)]
+struct Endpoint {
+    id: RouteId,
+    methods: Option<MethodFilter>,
+}
+
+#[derive(Debug, Clone)]
+struct ParamNode {
+    name: String,
+    constraint: Option<Constraint>,
+    node: Node,
+}
+
+// `*name` 匹配剩余的整个路径（至少一个字符），包括其中的 `/`
+#[derive(Debug, Clone)]
+struct CatchAll {
+    name: String,
+    routes: Vec<Endpoint>,
+    methods: MethodFilter,
+}
+
+/// The result of looking up a path in the tree.
+pub(crate) struct Match<'n, 'p> {
+    pub(crate) routes: Vec<RouteId>,
+    pub(crate) params: Vec<(&'n str, &'p str)>,
+    // 匹配到的路由中不接受请求方法的那些接受的方法，用于 405 响应的 `Allow`
+    pub(crate) rejected: MethodFilter,
+    // 只匹配到了嵌套路由的前缀
+    pub(crate) nested: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Found {
+    // 匹配到了路由，其中是否有接受请求方法的，以及其余路由接受的方法
+    Route {
+        accepted: bool,
+        rejected: MethodFilter,
+    },
+    Nested,
+}
+
+impl Node {
+    pub(crate) fn insert(
+        &mut self,
+        pattern: &PathPattern,
+        id: RouteId,
+        methods: Option<MethodFilter>,
+    ) -> Result<(), InsertError> {
+        // 可选段会展开成多条路径，都指向同一个路由
+        for segments in pattern.variants() {
+            // 省略了 `*name?` 时，`/assets/` 和 `/assets` 一样匹配
+            let omits_catch_all = matches!(
+                pattern.segments().get(segments.len()),
+                Some(Segment::CatchAll(_))
+            );
+
+            let (routes, existing) = match segments.split_last() {
+                Some((Segment::CatchAll(name), parents)) => {
+                    let node = self.descend(parents)?;
+                    let catch_all = node.catch_all.get_or_insert_with(|| {
+                        Box::new(CatchAll {
+                            name: name.clone(),
+                            routes: Vec::new(),
+                            methods: MethodFilter::empty(),
+                        })
+                    });
+
+                    if catch_all.name != *name {
+                        return Err(InsertError::CaptureConflict {
+                            existing: format!("*{}", catch_all.name),
+                            new: format!("*{}", name),
+                        });
+                    }
+
+                    (&mut catch_all.routes, &mut catch_all.methods)
+                }
+                _ => {
+                    let node = self.descend(segments)?;
+
+                    if omits_catch_all {
+                        let slash = node.statics.entry(String::new()).or_default();
+                        add_route(&mut slash.routes, &mut slash.methods, id, methods)?;
+                    }
+
+                    (&mut node.routes, &mut node.methods)
+                }
+            };
+
+            add_route(routes, existing, id, methods)?;
+        }
+
+        Ok(())
+    }
+
+    /// Insert a route matching `pattern` and anything below it.
+    pub(crate) fn insert_prefix(
+        &mut self,
+        pattern: &PathPattern,
+        id: RouteId,
+    ) -> Result<(), InsertError> {
+        self.descend(pattern.prefix_segments())?.nested.push(id);
+        Ok(())
+    }
+
+    fn descend(&mut self, segments: &[Segment]) -> Result<&mut Node, InsertError> {
+        let mut node = self;
+
+        for segment in segments {
+            node = match segment {
+                Segment::Static(part) => node.statics.entry(part.clone()).or_default(),
+                Segment::Param(name, constraint) => {
+                    // 约束不同的参数是不同的分支，约束相同时名称也必须相同
+                    let idx = match node
+                        .params
+                        .iter()
+                        .position(|param| param.constraint == *constraint)
+                    {
+                        Some(idx) => idx,
+                        None => {
+                            let idx = if constraint.is_some() {
+                                node.params
+                                    .iter()
+                                    .position(|param| param.constraint.is_none())
+                                    .unwrap_or(node.params.len())
+                            } else {
+                                node.params.len()
+                            };
+
+                            node.params.insert(
+                                idx,
+                                ParamNode {
+                                    name: name.clone(),
+                                    constraint: constraint.clone(),
+                                    node: Node::default(),
+                                },
+                            );
+                            idx
+                        }
+                    };
+
+                    let param = &mut node.params[idx];
+
+                    if param.name != *name {
+                        return Err(InsertError::CaptureConflict {
+                            existing: display_param(&param.name, param.constraint.as_ref()),
+                            new: display_param(name, constraint.as_ref()),
+                        });
+                    }
+
+                    &mut param.node
+                }
+                Segment::CatchAll(_) => {
+                    unreachable!("catch-all segments are only allowed at the end of a route")
+                }
+            };
+        }
+
+        Ok(node)
+    }
+
+    /// Look up the routes for a request to `path` with `method`.
+    ///
+    /// The routes of the most specific match come first, followed by the
+    /// closest nested route above them if none of them accepts `method`.
+    pub(crate) fn at<'n, 'p>(&'n self, path: &'p str, method: &Method) -> Option<Match<'n, 'p>> {
+        let mut params = Vec::new();
+        let mut routes = Vec::new();
+        let path = path.strip_prefix('/').unwrap_or(path);
+        // `/` 不包含任何路径段
+        let path = if path.is_empty() { None } else { Some(path) };
+
+        let found = self.find(path, method, &mut params, &mut routes)?;
+
+        let rejected = match found {
+            Found::Route { rejected, .. } => rejected,
+            Found::Nested => MethodFilter::empty(),
+        };
+
+        Some(Match {
+            routes,
+            params,
+            rejected,
+            nested: matches!(found, Found::Nested),
+        })
+    }
+
+    // `path` 是去掉前导 `/` 之后剩余的路径，`None` 表示已经走到末尾
+    fn find<'n, 'p>(
+        &'n self,
+        path: Option<&'p str>,
+        method: &Method,
+        params: &mut Vec<(&'n str, &'p str)>,
+        routes: &mut Vec<RouteId>,
+    ) -> Option<Found> {
+        if let Some(path) = path {
+            let (segment, rest) = match path.find('/') {
+                Some(idx) => (&path[..idx], Some(&path[idx + 1..])),
+                None => (path, None),
+            };
+
+            if let Some(child) = self.statics.get(segment) {
+                if let Some(found) = child.find(rest, method, params, routes) {
+                    return Some(self.or_nested(found, routes));
+                }
+            }
+
+            for param in &self.params {
+                if param_matches(param.constraint.as_ref(), segment) {
+                    params.push((&param.name, segment));
+
+                    if let Some(found) = param.node.find(rest, method, params, routes) {
+                        return Some(self.or_nested(found, routes));
+                    }
+
+                    params.pop();
+                }
+            }
+
+            if let Some(catch_all) = &self.catch_all {
+                if !path.is_empty() {
+                    params.push((&catch_all.name, path));
+                    let found = push_routes(&catch_all.routes, method, routes);
+                    return Some(self.or_nested(found, routes));
+                }
+            }
+        } else if !self.routes.is_empty() {
+            let found = push_routes(&self.routes, method, routes);
+            return Some(self.or_nested(found, routes));
+        }
+
+        if self.nested.is_empty() {
+            None
+        } else {
+            routes.extend_from_slice(&self.nested);
+            Some(Found::Nested)
+        }
+    }
+
+    // 更深的路由都不接受请求的方法时，这个节点上的嵌套路由也可以处理这个请求
+    fn or_nested(&self, found: Found, routes: &mut Vec<RouteId>) -> Found {
+        match found {
+            Found::Route {
+                accepted: false,
+                rejected,
+            } if !self.nested.is_empty() => {
+                routes.extend_from_slice(&self.nested);
+                Found::Route {
+                    accepted: true,
+                    rejected,
+                }
+            }
+            found => found,
+        }
+    }
+}
+
+// 明确接受这个方法的路由优先，然后是 `GET` 隐含的 `HEAD`，它们一定会处理请求，所以只留下
+// 第一个；没有的话依次尝试不知道方法的服务。不接受这个方法的路由不会被调用，只记下它们的
+// 方法，用于 405 响应的 `Allow`
+fn push_routes(endpoints: &[Endpoint], method: &Method, routes: &mut Vec<RouteId>) -> Found {
+    let mut explicit = None;
+    let mut implicit = None;
+    let mut unknown = Vec::new();
+    let mut rejected = MethodFilter::empty();
+
+    for endpoint in endpoints {
+        match endpoint.methods {
+            Some(methods) if methods.explicit().matches(method) => {
+                explicit.get_or_insert(endpoint.id);
+            }
+            Some(methods) if methods.matches(method) => {
+                implicit.get_or_insert(endpoint.id);
+            }
+            Some(methods) => rejected |= methods,
+            None => unknown.push(endpoint.id),
+        }
+    }
+
+    let accepted = match explicit.or(implicit) {
+        Some(id) => {
+            routes.push(id);
+            true
+        }
+        None => {
+            let accepted = !unknown.is_empty();
+            routes.extend(unknown);
+            accepted
+        }
+    };
+
+    Found::Route { accepted, rejected }
+}
+
+fn add_route(
+    routes: &mut Vec<Endpoint>,
+    existing: &mut MethodFilter,
+    id: RouteId,
+    methods: Option<MethodFilter>,
+) -> Result<(), InsertError> {
+    // 不知道方法的服务可能接受任何方法，不参与冲突检查
+    if let Some(methods) = methods.map(MethodFilter::explicit) {
+        let overlap = *existing & methods;
+        if !overlap.is_empty() {
+            return Err(InsertError::MethodConflict(overlap));
+        }
+
+        *existing |= methods;
+    }
+
+    routes.push(Endpoint { id, methods });
+    Ok(())
+}
+
+fn display_param(name: &str, constraint: Option<&Constraint>) -> String {
+    match constraint {
+        Some(constraint) => format!(":{}{}", name, constraint),
+        None => format!(":{}", name),
+    }
+}
+
+/// Error returned when a route cannot be added to the tree.
+#[derive(Debug)]
+pub(crate) enum InsertError {
+    CaptureConflict { existing: String, new: String },
+    MethodConflict(MethodFilter),
+    DuplicateName(String),
+}
+
+impl fmt::Display for InsertError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::CaptureConflict { existing, new } => write!(
+                f,
+                "capture `{}` conflicts with existing capture `{}` at the same position",
+                new, existing
+            ),
+            Self::MethodConflict(methods) => write!(
+                f,
+                "another route for the same path already handles `{}`",
+                methods.names().collect::<Vec<_>>().join("`, `")
+            ),
+            Self::DuplicateName(name) => {
+                write!(f, "the name `{}` is already used by another route", name)
+            }
+        }
+    }
+}
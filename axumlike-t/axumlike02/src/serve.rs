@@ -0,0 +1,619 @@
+//! Serving an app with graceful shutdown.
+//!
+//! [`Server`](crate::Server) drops the requests that are still being handled
+//! when it is stopped. [`GracefulServer`] stops accepting connections when a
+//! shutdown signal fires and waits for the connections that are open to
+//! finish their requests first.
+
+use crate::BoxError;
+use bytes::Bytes;
+use futures_util::future::{self, BoxFuture, Either};
+use http::{Request, Response};
+use hyper::{
+    server::{
+        accept::Accept,
+        conn::{AddrIncoming, AddrStream, Http},
+    },
+    Body,
+};
+use std::{
+    future::Future,
+    io,
+    net::SocketAddr,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+    time::Duration,
+};
+use tokio::{
+    io::{AsyncRead, AsyncWrite, ReadBuf},
+    sync::{mpsc, watch},
+};
+use tower::ServiceExt;
+use tower_service::Service;
+
+// 强制关闭之后，最多再等这么久让连接结束
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// A server that drains its connections before shutting down.
+///
+/// ```rust,no_run
+/// use axumlike02::{handler::get, serve::GracefulServer, Router};
+/// use std::time::Duration;
+///
+/// # async {
+/// let app = Router::new().route("/", get(|| async { "Hello, World!" }));
+///
+/// // Anything that completes when the server should stop, for example
+/// // `tokio::signal::ctrl_c` or the receiving half of a channel.
+/// let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+///
+/// let shutdown = GracefulServer::bind(&"0.0.0.0:3000".parse().unwrap())
+///     .unwrap()
+///     .drain_timeout(Duration::from_secs(10))
+///     .serve(app.into_make_service(), async {
+///         stopped.await.ok();
+///     })
+///     .await
+///     .unwrap();
+///
+/// println!("{} connections were closed", shutdown.forced());
+/// # };
+/// ```
+///
+/// When the signal completes the server stops accepting connections, closes
+/// the idle ones and waits for the others to finish the request they are
+/// handling. Upgraded connections, such as WebSockets, are waited for until
+/// they are closed. Connections still open when the drain timeout elapses are
+/// closed without waiting for their response. An upgraded connection that
+/// nothing reads from or writes to can't notice that it was closed, so the
+/// server returns at most a second after the drain timeout even if such a
+/// connection is still around.
+#[derive(Debug)]
+pub struct GracefulServer {
+    incoming: AddrIncoming,
+    drain_timeout: Duration,
+}
+
+impl GracefulServer {
+    /// Bind a server to `addr`.
+    ///
+    /// Binding to port `0` picks a free port, which can be read with
+    /// [`GracefulServer::local_addr`].
+    pub fn bind(addr: &SocketAddr) -> hyper::Result<Self> {
+        let incoming = AddrIncoming::bind(addr)?;
+
+        Ok(Self {
+            incoming,
+            drain_timeout: Duration::from_secs(30),
+        })
+    }
+
+    /// The address the server is accepting connections on.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.incoming.local_addr()
+    }
+
+    /// How long to wait for open connections after the shutdown signal.
+    ///
+    /// Defaults to 30 seconds.
+    pub fn drain_timeout(mut self, timeout: Duration) -> Self {
+        self.drain_timeout = timeout;
+        self
+    }
+
+    /// Serve connections with the services made by `make_service` until
+    /// `signal` completes, then drain the open connections.
+    ///
+    /// If `make_service` fails for a connection the error is logged and that
+    /// connection is closed, the server keeps accepting others.
+    ///
+    /// # Errors
+    ///
+    /// Fails if accepting a connection fails or `make_service` fails to become
+    /// ready. Open connections are drained before the error is returned.
+    pub async fn serve<M, ME, S, B, F>(
+        mut self,
+        mut make_service: M,
+        signal: F,
+    ) -> Result<Shutdown, BoxError>
+    where
+        M: for<'a> Service<&'a AddrStream, Response = S, Error = ME>,
+        ME: Into<BoxError>,
+        S: Service<Request<Body>, Response = Response<B>> + Send + 'static,
+        S::Error: Into<BoxError>,
+        S::Future: Send + 'static,
+        B: http_body::Body<Data = Bytes> + Send + Sync + 'static,
+        B::Error: Into<BoxError>,
+        F: Future<Output = ()>,
+    {
+        let http = Http::new();
+
+        // 开始排空连接 / 强制关闭连接 的通知
+        let (drain_tx, drain_rx) = watch::channel(());
+        let (close_tx, close_rx) = watch::channel(());
+        let open = OpenConnections::new();
+
+        futures_util::pin_mut!(signal);
+
+        let result = loop {
+            let accept = future::poll_fn(|cx| Pin::new(&mut self.incoming).poll_accept(cx));
+
+            let stream = match future::select(signal.as_mut(), accept).await {
+                Either::Left(_) | Either::Right((None, _)) => break Ok(()),
+                Either::Right((Some(Ok(stream)), _)) => stream,
+                Either::Right((Some(Err(err)), _)) => break Err(err.into()),
+            };
+
+            let make_service = match make_service.ready().await {
+                Ok(make_service) => make_service,
+                Err(err) => break Err(err.into()),
+            };
+
+            // 只影响这一个连接，不停止服务
+            let svc = match make_service.call(&stream).await {
+                Ok(svc) => svc,
+                Err(err) => {
+                    let err: BoxError = err.into();
+                    tracing::warn!(
+                        remote_addr = %stream.remote_addr(),
+                        "failed to make service for connection: {}",
+                        err
+                    );
+                    continue;
+                }
+            };
+
+            let io = ConnectionIo::new(stream, open.guard(), close_rx.clone());
+            let conn = http.serve_connection(io, svc).with_upgrades();
+            let mut drain_rx = drain_rx.clone();
+            let mut close_rx = close_rx.clone();
+
+            tokio::spawn(async move {
+                futures_util::pin_mut!(conn);
+
+                let drain = drain_rx.changed();
+                futures_util::pin_mut!(drain);
+                if let Either::Right(_) = future::select(conn.as_mut(), drain).await {
+                    conn.as_mut().graceful_shutdown();
+
+                    let close = close_rx.changed();
+                    futures_util::pin_mut!(close);
+                    future::select(conn, close).await;
+                }
+            });
+        };
+
+        // 停止接受新连接
+        drop(self.incoming);
+        let _ = drain_tx.send(());
+
+        let mut forced = 0;
+        let OpenConnections { tx, mut rx, count } = open;
+        drop(tx);
+
+        let drained = tokio::time::timeout(self.drain_timeout, rx.recv())
+            .await
+            .is_ok();
+
+        if !drained {
+            forced = count.load(Ordering::SeqCst);
+            let _ = close_tx.send(());
+
+            // 升级后的连接只有在读写时才会发现被关闭了，没有人读写的连接不再等待
+            let _ = tokio::time::timeout(CLOSE_TIMEOUT, rx.recv()).await;
+        }
+
+        result.map(|()| Shutdown { forced })
+    }
+}
+
+// 还开着的连接。每个连接的 IO 持有一个发送端，全部关闭后接收端才会结束
+struct OpenConnections {
+    tx: mpsc::Sender<()>,
+    rx: mpsc::Receiver<()>,
+    count: Arc<AtomicUsize>,
+}
+
+impl OpenConnections {
+    fn new() -> Self {
+        let (tx, rx) = mpsc::channel(1);
+
+        Self {
+            tx,
+            rx,
+            count: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    fn guard(&self) -> OpenGuard {
+        self.count.fetch_add(1, Ordering::SeqCst);
+
+        OpenGuard {
+            _tx: self.tx.clone(),
+            count: self.count.clone(),
+        }
+    }
+}
+
+struct OpenGuard {
+    _tx: mpsc::Sender<()>,
+    count: Arc<AtomicUsize>,
+}
+
+impl Drop for OpenGuard {
+    fn drop(&mut self) {
+        self.count.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+// 连接升级（比如 WebSocket）之后 hyper 会把 IO 交给升级后的连接，
+// 所以由 IO 来记录连接是否还开着，并在强制关闭时让读写失败
+struct ConnectionIo {
+    io: AddrStream,
+    _open: OpenGuard,
+    // 读和写可能在不同的任务中，分别等待关闭通知
+    read_close: CloseSignal,
+    write_close: CloseSignal,
+}
+
+impl ConnectionIo {
+    fn new(io: AddrStream, open: OpenGuard, close_rx: watch::Receiver<()>) -> Self {
+        Self {
+            io,
+            _open: open,
+            read_close: CloseSignal::new(close_rx.clone()),
+            write_close: CloseSignal::new(close_rx),
+        }
+    }
+}
+
+struct CloseSignal {
+    // 收到通知之后为 `None`
+    changed: Option<BoxFuture<'static, ()>>,
+}
+
+impl CloseSignal {
+    fn new(mut close_rx: watch::Receiver<()>) -> Self {
+        let changed = Box::pin(async move {
+            let _ = close_rx.changed().await;
+        });
+
+        Self {
+            changed: Some(changed),
+        }
+    }
+
+    fn poll_closed(&mut self, cx: &mut Context<'_>) -> io::Result<()> {
+        if let Some(changed) = &mut self.changed {
+            if changed.as_mut().poll(cx).is_pending() {
+                return Ok(());
+            }
+            self.changed = None;
+        }
+
+        Err(io::Error::new(
+            io::ErrorKind::ConnectionAborted,
+            "connection closed by server shutdown",
+        ))
+    }
+}
+
+impl AsyncRead for ConnectionIo {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        self.read_close.poll_closed(cx)?;
+        Pin::new(&mut self.io).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for ConnectionIo {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.write_close.poll_closed(cx)?;
+        Pin::new(&mut self.io).poll_write(cx, buf)
+    }
+
+    fn poll_write_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[io::IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        self.write_close.poll_closed(cx)?;
+        Pin::new(&mut self.io).poll_write_vectored(cx, bufs)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.io.is_write_vectored()
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.write_close.poll_closed(cx)?;
+        Pin::new(&mut self.io).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.io).poll_shutdown(cx)
+    }
+}
+
+/// What happened to the open connections when a [`GracefulServer`] shut
+/// down.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Shutdown {
+    forced: usize,
+}
+
+impl Shutdown {
+    /// The number of connections that were closed because they didn't finish
+    /// before the drain timeout.
+    pub fn forced(&self) -> usize {
+        self.forced
+    }
+
+    /// Whether all connections finished before the drain timeout.
+    pub fn is_graceful(&self) -> bool {
+        self.forced == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{handler::get, Router};
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        sync::oneshot,
+    };
+
+    // 第一个连接创建服务失败，之后的连接正常
+    #[derive(Clone)]
+    struct FailFirst<S> {
+        calls: Arc<AtomicUsize>,
+        svc: S,
+    }
+
+    impl<'a, S> Service<&'a AddrStream> for FailFirst<S>
+    where
+        S: Clone,
+    {
+        type Response = S;
+        type Error = &'static str;
+        type Future = future::Ready<Result<S, &'static str>>;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, _stream: &'a AddrStream) -> Self::Future {
+            if self.calls.fetch_add(1, Ordering::SeqCst) == 0 {
+                future::ready(Err("no service for this connection"))
+            } else {
+                future::ready(Ok(self.svc.clone()))
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn make_service_error_drops_connection() {
+        let app = Router::new().route("/", get(|| async { "Hello, World!" }));
+        let make_service = FailFirst {
+            calls: Arc::new(AtomicUsize::new(0)),
+            svc: app,
+        };
+
+        let server = GracefulServer::bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+        let addr = server.local_addr();
+        let (stop, stopped) = oneshot::channel::<()>();
+        let server = tokio::spawn(server.serve(make_service, async {
+            stopped.await.ok();
+        }));
+
+        let client = hyper::Client::new();
+        let uri = format!("http://{}/", addr).parse::<http::Uri>().unwrap();
+
+        assert!(client.get(uri.clone()).await.is_err());
+
+        let res = client.get(uri).await.unwrap();
+        let body = hyper::body::to_bytes(res.into_body()).await.unwrap();
+        assert_eq!(body, "Hello, World!");
+
+        stop.send(()).unwrap();
+        let shutdown = server.await.unwrap().unwrap();
+        assert!(shutdown.is_graceful());
+    }
+
+    #[tokio::test]
+    async fn drain_waits_for_in_flight_requests() {
+        let app = Router::new().route(
+            "/",
+            get(|| async {
+                tokio::time::sleep(Duration::from_millis(200)).await;
+                "done"
+            }),
+        );
+
+        let server = GracefulServer::bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+        let addr = server.local_addr();
+        let (stop, stopped) = oneshot::channel::<()>();
+        let server = tokio::spawn(server.serve(app.into_make_service(), async {
+            stopped.await.ok();
+        }));
+
+        let uri = format!("http://{}/", addr).parse::<http::Uri>().unwrap();
+        let res = tokio::spawn(hyper::Client::new().get(uri));
+
+        // 等请求到达服务端之后再停止
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        stop.send(()).unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(!server.is_finished());
+
+        let res = res.await.unwrap().unwrap();
+        let body = hyper::body::to_bytes(res.into_body()).await.unwrap();
+        assert_eq!(body, "done");
+
+        let shutdown = server.await.unwrap().unwrap();
+        assert!(shutdown.is_graceful());
+        assert_eq!(shutdown.forced(), 0);
+    }
+
+    #[tokio::test]
+    async fn drain_timeout_closes_in_flight_requests() {
+        let app = Router::new().route("/", get(future::pending::<&'static str>));
+
+        let server = GracefulServer::bind(&"127.0.0.1:0".parse().unwrap())
+            .unwrap()
+            .drain_timeout(Duration::from_millis(100));
+        let addr = server.local_addr();
+        let (stop, stopped) = oneshot::channel::<()>();
+        let server = tokio::spawn(server.serve(app.into_make_service(), async {
+            stopped.await.ok();
+        }));
+
+        let uri = format!("http://{}/", addr).parse::<http::Uri>().unwrap();
+        let res = tokio::spawn(hyper::Client::new().get(uri));
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        stop.send(()).unwrap();
+
+        let shutdown = server.await.unwrap().unwrap();
+        assert!(!shutdown.is_graceful());
+        assert_eq!(shutdown.forced(), 1);
+
+        assert!(res.await.unwrap().is_err());
+    }
+
+    #[tokio::test]
+    async fn drain_timeout_does_not_wait_for_idle_upgrades() {
+        // 升级之后一直持有连接，但是从不读写
+        let app = Router::new().route(
+            "/",
+            get(|req: Request<Body>| async move {
+                tokio::spawn(async move {
+                    let _upgraded = hyper::upgrade::on(req).await;
+                    future::pending::<()>().await;
+                });
+
+                Response::builder()
+                    .status(http::StatusCode::SWITCHING_PROTOCOLS)
+                    .header(http::header::CONNECTION, "upgrade")
+                    .header(http::header::UPGRADE, "test")
+                    .body(Body::empty())
+                    .unwrap()
+            }),
+        );
+
+        let server = GracefulServer::bind(&"127.0.0.1:0".parse().unwrap())
+            .unwrap()
+            .drain_timeout(Duration::from_millis(100));
+        let addr = server.local_addr();
+        let (stop, stopped) = oneshot::channel::<()>();
+        let server = tokio::spawn(server.serve(app.into_make_service(), async {
+            stopped.await.ok();
+        }));
+
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        let request = "GET / HTTP/1.1\r\n\
+            host: localhost\r\n\
+            connection: upgrade\r\n\
+            upgrade: test\r\n\r\n";
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut buf = [0; 1024];
+        let n = stream.read(&mut buf).await.unwrap();
+        assert!(buf[..n].starts_with(b"HTTP/1.1 101"));
+
+        stop.send(()).unwrap();
+        let shutdown = tokio::time::timeout(Duration::from_secs(5), server)
+            .await
+            .expect("server should stop even if the upgraded connection is idle")
+            .unwrap()
+            .unwrap();
+        assert_eq!(shutdown.forced(), 1);
+    }
+
+    #[cfg(feature = "ws")]
+    mod upgraded {
+        use super::*;
+        use crate::extract::ws::{Message, WebSocketUpgrade};
+        use crate::response::IntoResponse;
+        use futures_util::{SinkExt, StreamExt};
+        use tokio_tungstenite::tungstenite;
+
+        async fn echo(ws: WebSocketUpgrade) -> impl IntoResponse {
+            ws.on_upgrade(|mut socket| async move {
+                while let Some(Ok(msg)) = socket.recv().await {
+                    if let Message::Close(_) = msg {
+                        break;
+                    }
+                    if socket.send(msg).await.is_err() {
+                        break;
+                    }
+                }
+            })
+        }
+
+        #[tokio::test]
+        async fn drain_waits_for_websockets() {
+            let app = Router::new().route("/ws", get(echo));
+
+            let server = GracefulServer::bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+            let addr = server.local_addr();
+            let (stop, stopped) = oneshot::channel::<()>();
+            let server = tokio::spawn(server.serve(app.into_make_service(), async {
+                stopped.await.ok();
+            }));
+
+            let (mut socket, _) = tokio_tungstenite::connect_async(format!("ws://{}/ws", addr))
+                .await
+                .unwrap();
+
+            stop.send(()).unwrap();
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            assert!(!server.is_finished());
+
+            // 排空期间 WebSocket 还可以继续使用
+            let hello = tungstenite::Message::Text("hello".to_owned());
+            socket.send(hello.clone()).await.unwrap();
+            assert_eq!(socket.next().await.unwrap().unwrap(), hello);
+
+            socket.close(None).await.unwrap();
+            let shutdown = server.await.unwrap().unwrap();
+            assert!(shutdown.is_graceful());
+        }
+
+        #[tokio::test]
+        async fn drain_timeout_closes_websockets() {
+            let app = Router::new().route("/ws", get(echo));
+
+            let server = GracefulServer::bind(&"127.0.0.1:0".parse().unwrap())
+                .unwrap()
+                .drain_timeout(Duration::from_millis(100));
+            let addr = server.local_addr();
+            let (stop, stopped) = oneshot::channel::<()>();
+            let server = tokio::spawn(server.serve(app.into_make_service(), async {
+                stopped.await.ok();
+            }));
+
+            let (mut socket, _) = tokio_tungstenite::connect_async(format!("ws://{}/ws", addr))
+                .await
+                .unwrap();
+
+            stop.send(()).unwrap();
+            let shutdown = server.await.unwrap().unwrap();
+            assert_eq!(shutdown.forced(), 1);
+
+            // 服务端已经关闭了连接
+            assert!(!matches!(socket.next().await, Some(Ok(_))));
+        }
+    }
+}
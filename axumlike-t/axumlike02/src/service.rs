@@ -2,7 +2,10 @@ use crate::BoxError;
 use crate::{
     body::BoxBody,
     response::IntoResponse,
-    router::{empty_router::EmptyRouter, MethodFilter},
+    router::{
+        empty_router::{allow_methods, EmptyRouter},
+        MethodFilter, RouteMethods,
+    },
 };
 use bytes::Bytes;
 use http::{Request, Response};
@@ -15,6 +18,9 @@ use tower::{util::Oneshot, ServiceExt as _};
 use tower_service::Service;
 
 pub mod future;
+mod serve_dir;
+
+pub use self::serve_dir::ServeDir;
 
 /// Route requests to the given service regardless of the HTTP method.
 ///
@@ -51,7 +57,7 @@ where
 /// # Example
 ///
 /// ```rust
-/// use axum::{
+/// use axumlike02::{
 ///     http::Request,
 ///     Router,
 ///     service,
@@ -67,7 +73,7 @@ where
 /// // Requests to `GET /` will go to `service`.
 /// let app = Router::new().route("/", service::get(service));
 /// # async {
-/// # axum::Server::bind(&"".parse().unwrap()).serve(app.into_make_service()).await.unwrap();
+/// # axumlike02::Server::bind(&"".parse().unwrap()).serve(app.into_make_service()).await.unwrap();
 /// # };
 /// ```
 ///
@@ -146,12 +152,12 @@ where
 /// # Example
 ///
 /// ```rust
-/// use axum::{
+/// use axumlike02::{
 ///     http::Request,
 ///     handler::on,
 ///     service,
 ///     Router,
-///     routing::MethodFilter,
+///     router::MethodFilter,
 /// };
 /// use http::Response;
 /// use std::convert::Infallible;
@@ -164,15 +170,17 @@ where
 /// // Requests to `POST /` will go to `service`.
 /// let app = Router::new().route("/", service::on(MethodFilter::POST, service));
 /// # async {
-/// # axum::Server::bind(&"".parse().unwrap()).serve(app.into_make_service()).await.unwrap();
+/// # axumlike02::Server::bind(&"".parse().unwrap()).serve(app.into_make_service()).await.unwrap();
 /// # };
 /// ```
 pub fn on<S, B>(method: MethodFilter, svc: S) -> OnMethod<S, EmptyRouter<S::Error>, B>
 where
     S: Service<Request<B>> + Clone,
 {
+
     OnMethod {
         method,
+        methods: method,
         svc,
         fallback: EmptyRouter::method_not_allowed(),
         _request_body: PhantomData,
@@ -184,6 +192,8 @@ where
 #[derive(Debug)] // TODO(david): don't require debug for B
 pub struct OnMethod<S, F, B> {
     pub(crate) method: MethodFilter,
+    // 整条链接受的方法
+    pub(crate) methods: MethodFilter,
     pub(crate) svc: S,
     pub(crate) fallback: F,
     pub(crate) _request_body: PhantomData<fn() -> B>,
@@ -197,6 +207,7 @@ where
     fn clone(&self) -> Self {
         Self {
             method: self.method,
+            methods: self.methods,
             svc: self.svc.clone(),
             fallback: self.fallback.clone(),
             _request_body: PhantomData,
@@ -241,12 +252,12 @@ impl<S, F, B> OnMethod<S, F, B> {
     /// # Example
     ///
     /// ```rust
-    /// use axum::{
+    /// use axumlike02::{
     ///     http::Request,
     ///     handler::on,
     ///     service,
     ///     Router,
-    ///     routing::MethodFilter,
+    ///     router::MethodFilter,
     /// };
     /// use http::Response;
     /// use std::convert::Infallible;
@@ -264,7 +275,7 @@ impl<S, F, B> OnMethod<S, F, B> {
     /// // `other_service`.
     /// let app = Router::new().route("/", service::post(service).get(other_service));
     /// # async {
-    /// # axum::Server::bind(&"".parse().unwrap()).serve(app.into_make_service()).await.unwrap();
+    /// # axumlike02::Server::bind(&"".parse().unwrap()).serve(app.into_make_service()).await.unwrap();
     /// # };
     /// ```
     ///
@@ -290,6 +301,10 @@ impl<S, F, B> OnMethod<S, F, B> {
 
     /// Chain an additional service that will only accept `OPTIONS` requests.
     ///
+    /// Without one, `OPTIONS` requests are answered with `200 OK` and an
+    /// `Allow` header listing the methods accepted by the chain, the same
+    /// header sent with `405 Method Not Allowed` responses.
+    ///
     /// See [`OnMethod::get`] for an example.
     pub fn options<T>(self, svc: T) -> OnMethod<T, Self, B>
     where
@@ -344,12 +359,12 @@ impl<S, F, B> OnMethod<S, F, B> {
     /// # Example
     ///
     /// ```rust
-    /// use axum::{
+    /// use axumlike02::{
     ///     http::Request,
     ///     handler::on,
     ///     service,
     ///     Router,
-    ///     routing::MethodFilter,
+    ///     router::MethodFilter,
     /// };
     /// use http::Response;
     /// use std::convert::Infallible;
@@ -366,15 +381,17 @@ impl<S, F, B> OnMethod<S, F, B> {
     /// // Requests to `DELETE /` will go to `service`
     /// let app = Router::new().route("/", service::on(MethodFilter::DELETE, service));
     /// # async {
-    /// # axum::Server::bind(&"".parse().unwrap()).serve(app.into_make_service()).await.unwrap();
+    /// # axumlike02::Server::bind(&"".parse().unwrap()).serve(app.into_make_service()).await.unwrap();
     /// # };
     /// ```
     pub fn on<T>(self, method: MethodFilter, svc: T) -> OnMethod<T, Self, B>
     where
         T: Service<Request<B>> + Clone,
     {
+
         OnMethod {
             method,
+            methods: method | self.methods,
             svc,
             fallback: self,
             _request_body: PhantomData,
@@ -394,6 +411,12 @@ impl<S, F, B> OnMethod<S, F, B> {
     }
 }
 
+impl<S, F, B> RouteMethods for OnMethod<S, F, B> {
+    fn methods(&self) -> Option<MethodFilter> {
+        Some(self.methods)
+    }
+}
+
 // this is identical to `routing::OnMethod`'s implementation. Would be nice to find a way to clean
 // that up, but not sure its possible.
 impl<S, F, B, ResBody> Service<Request<B>> for OnMethod<S, F, B>
@@ -411,7 +434,7 @@ where
         Poll::Ready(Ok(()))
     }
 
-    fn call(&mut self, req: Request<B>) -> Self::Future {
+    fn call(&mut self, mut req: Request<B>) -> Self::Future {
         use crate::util::Either;
 
         let req_method = req.method().clone();
@@ -420,6 +443,7 @@ where
             let fut = self.svc.clone().oneshot(req);
             Either::A { inner: fut }
         } else {
+            allow_methods(&mut req, self.method);
             let fut = self.fallback.clone().oneshot(req);
             Either::B { inner: fut }
         };
@@ -464,6 +488,15 @@ impl<S, F, B> HandleError<S, F, B> {
     }
 }
 
+impl<S, F, B> RouteMethods for HandleError<S, F, B>
+where
+    S: RouteMethods,
+{
+    fn methods(&self) -> Option<MethodFilter> {
+        self.inner.methods()
+    }
+}
+
 impl<S, F, B> fmt::Debug for HandleError<S, F, B>
 where
     S: fmt::Debug,
@@ -5,11 +5,12 @@ use crate::{
     BoxError,
 };
 use bytes::Bytes;
-use futures_util::ready;
+use futures_util::{future::BoxFuture, ready};
 use http::{Method, Request, Response};
 use http_body::Empty;
 use pin_project_lite::pin_project;
 use std::{
+    convert::Infallible,
     future::Future,
     pin::Pin,
     task::{Context, Poll},
@@ -96,4 +97,9 @@ where
             Poll::Ready(Ok(response))
         }
     }
-}
\ No newline at end of file
+}
+opaque_future! {
+    /// Response future for [`ServeDir`](super::ServeDir).
+    pub type ServeDirFuture =
+        BoxFuture<'static, Result<Response<BoxBody>, Infallible>>;
+}
@@ -0,0 +1,614 @@
+// 静态文件服务：把请求路径映射到目录中的文件
+use super::future::ServeDirFuture;
+use crate::{
+    body::{box_body, BoxBody, Body},
+    router::{route::NestedPrefix, MethodFilter, RouteMethods},
+};
+use http::{header, request, HeaderMap, HeaderValue, Method, Request, Response, StatusCode};
+use percent_encoding::percent_decode_str;
+use std::{
+    convert::Infallible,
+    io::{self, SeekFrom},
+    path::{Component, Path, PathBuf},
+    sync::Arc,
+    task::{Context, Poll},
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tokio::{
+    fs::File,
+    io::{AsyncReadExt, AsyncSeekExt},
+};
+use tokio_util::io::ReaderStream;
+use tower_service::Service;
+
+/// A [`Service`] that serves files from a directory.
+///
+/// The path of the request is looked up in the directory, so the service is
+/// usually mounted with [`Router::nest`](crate::Router::nest), which removes
+/// the prefix it's nested at from the path:
+///
+/// ```rust,no_run
+/// use axumlike02::{service::ServeDir, Router};
+///
+/// // `GET /assets/css/app.css` serves `public/css/app.css`.
+/// let app = Router::new().nest("/assets", ServeDir::new("public"));
+/// # async {
+/// # axumlike02::Server::bind(&"".parse().unwrap()).serve(app.into_make_service()).await.unwrap();
+/// # };
+/// ```
+///
+/// Requests for a directory are served its `index.html`, and redirected with
+/// `308 Permanent Redirect` to the path with a trailing slash if it doesn't
+/// have one, so relative links in the page work. Only `GET` and `HEAD`
+/// requests are accepted, and paths with `..` segments or NUL bytes are
+/// rejected with `404 Not Found` so files outside the directory can't be
+/// read.
+///
+/// Responses have a `Content-Type` guessed from the file extension, and
+/// `ETag` and `Last-Modified` headers. Requests with a matching
+/// `If-None-Match` or `If-Modified-Since` header get `304 Not Modified`, and
+/// requests with a single `Range` get the requested bytes with
+/// `206 Partial Content`. Files are streamed rather than read into memory.
+#[derive(Debug, Clone)]
+pub struct ServeDir {
+    base: Arc<Path>,
+}
+
+impl ServeDir {
+    /// Create a new `ServeDir` serving the files in `path`.
+    pub fn new<P>(path: P) -> Self
+    where
+        P: AsRef<Path>,
+    {
+
+        Self {
+            base: path.as_ref().into(),
+        }
+    }
+}
+
+impl RouteMethods for ServeDir {
+    fn methods(&self) -> Option<MethodFilter> {
+        Some(MethodFilter::GET | MethodFilter::HEAD)
+    }
+}
+
+impl<B> Service<Request<B>> for ServeDir {
+    type Response = Response<BoxBody>;
+    type Error = Infallible;
+    type Future = ServeDirFuture;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: Request<B>) -> Self::Future {
+        let base = self.base.clone();
+        let (parts, _) = req.into_parts();
+
+        ServeDirFuture {
+            future: Box::pin(async move { Ok(serve(&base, &parts).await) }),
+        }
+    }
+}
+
+async fn serve(base: &Path, parts: &request::Parts) -> Response<BoxBody> {
+    let method = parts.method.clone();
+    let headers = &parts.headers;
+
+    if method != Method::GET && method != Method::HEAD {
+        let mut res = empty_response(StatusCode::METHOD_NOT_ALLOWED);
+        res.headers_mut()
+            .insert(header::ALLOW, HeaderValue::from_static("GET,HEAD"));
+        return res;
+    }
+
+    let mut path = match resolve(base, parts.uri.path()) {
+        Some(path) => path,
+        None => return empty_response(StatusCode::NOT_FOUND),
+    };
+
+    let mut metadata = match tokio::fs::metadata(&path).await {
+        Ok(metadata) => metadata,
+        Err(err) => return io_error_response(err),
+    };
+
+    if metadata.is_dir() {
+        // 嵌套时需要完整的路径才能重定向
+        let original = match parts.extensions.get::<NestedPrefix>() {
+            Some(prefix) => prefix.original_path(parts.uri.path()),
+            None => parts.uri.path().to_owned(),
+        };
+
+        if !original.ends_with('/') {
+            return redirect_to_dir(&original, parts.uri.query());
+        }
+
+        path.push("index.html");
+        metadata = match tokio::fs::metadata(&path).await {
+            Ok(metadata) => metadata,
+            Err(err) => return io_error_response(err),
+        };
+    }
+
+    if !metadata.is_file() {
+        return empty_response(StatusCode::NOT_FOUND);
+    }
+
+    let len = metadata.len();
+    let modified = metadata.modified().ok();
+    let etag = etag(len, modified);
+    let last_modified = modified.and_then(|modified| {
+        HeaderValue::from_str(&httpdate::fmt_http_date(modified)).ok()
+    });
+
+    let mut res = if is_not_modified(headers, &etag, modified) {
+        empty_response(StatusCode::NOT_MODIFIED)
+    } else {
+        let range = headers
+            .get(header::RANGE)
+            .map_or(ByteRange::Full, |range| ByteRange::parse(range, len));
+
+        let (status, start, end) = match range {
+            ByteRange::Full => (StatusCode::OK, 0, len),
+            ByteRange::Partial(start, end) => (StatusCode::PARTIAL_CONTENT, start, end + 1),
+            ByteRange::Unsatisfiable => {
+                let mut res = empty_response(StatusCode::RANGE_NOT_SATISFIABLE);
+                res.headers_mut().insert(
+                    header::CONTENT_RANGE,
+                    HeaderValue::from_str(&format!("bytes */{}", len)).unwrap(),
+                );
+                return res;
+            }
+        };
+
+        let body = if method == Method::HEAD {
+            crate::body::empty()
+        } else {
+            match open_range(&path, start, end - start).await {
+                Ok(body) => body,
+                Err(err) => return io_error_response(err),
+            }
+        };
+
+        let mut res = Response::new(body);
+        *res.status_mut() = status;
+
+        let headers = res.headers_mut();
+        let mime = mime_guess::from_path(&path).first_or_octet_stream();
+        if let Ok(content_type) = HeaderValue::from_str(mime.as_ref()) {
+            headers.insert(header::CONTENT_TYPE, content_type);
+        }
+        headers.insert(header::CONTENT_LENGTH, HeaderValue::from(end - start));
+        headers.insert(header::ACCEPT_RANGES, HeaderValue::from_static("bytes"));
+
+        if status == StatusCode::PARTIAL_CONTENT {
+            headers.insert(
+                header::CONTENT_RANGE,
+                HeaderValue::from_str(&format!("bytes {}-{}/{}", start, end - 1, len)).unwrap(),
+            );
+        }
+
+        res
+    };
+
+    res.headers_mut().insert(header::ETAG, etag);
+    if let Some(last_modified) = last_modified {
+        res.headers_mut()
+            .insert(header::LAST_MODIFIED, last_modified);
+    }
+
+    res
+}
+
+/// Map the request path to a path inside `base`, rejecting paths that would
+/// escape it.
+fn resolve(base: &Path, path: &str) -> Option<PathBuf> {
+    let path = percent_decode_str(path).decode_utf8().ok()?;
+    let mut resolved = base.to_path_buf();
+
+    for segment in path.split('/') {
+        if segment.is_empty() || segment == "." {
+            continue;
+        }
+
+        // 每一段都必须是普通的文件名，`..`、盘符、`\`、NUL 等都会被拒绝
+        let mut components = Path::new(segment).components();
+        match (components.next(), components.next()) {
+            (Some(Component::Normal(name)), None) if !segment.contains(['\\', '\0']) => {
+                resolved.push(name)
+            }
+            _ => return None,
+        }
+    }
+
+    Some(resolved)
+}
+
+async fn open_range(path: &Path, start: u64, len: u64) -> io::Result<BoxBody> {
+    let mut file = File::open(path).await?;
+    if start > 0 {
+        file.seek(SeekFrom::Start(start)).await?;
+    }
+
+    let stream = ReaderStream::new(file.take(len));
+    Ok(box_body(Body::wrap_stream(stream)))
+}
+
+fn etag(len: u64, modified: Option<SystemTime>) -> HeaderValue {
+    let modified = modified
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |modified| modified.as_nanos());
+
+    HeaderValue::from_str(&format!("\"{:x}-{:x}\"", len, modified))
+        .expect("hex digits are valid header values")
+}
+
+// `If-None-Match` 存在时忽略 `If-Modified-Since`
+fn is_not_modified(headers: &HeaderMap, etag: &HeaderValue, modified: Option<SystemTime>) -> bool {
+    if let Some(if_none_match) = headers.get(header::IF_NONE_MATCH) {
+        let etag = etag.to_str().unwrap_or_default();
+
+        return if_none_match.to_str().is_ok_and(|tags| {
+            tags.split(',').map(str::trim).any(|tag| {
+                tag == "*" || tag.strip_prefix("W/").unwrap_or(tag) == etag
+            })
+        });
+    }
+
+    let since = headers
+        .get(header::IF_MODIFIED_SINCE)
+        .and_then(|since| since.to_str().ok())
+        .and_then(|since| httpdate::parse_http_date(since).ok());
+
+    match (since, modified) {
+        // HTTP 日期只精确到秒
+        (Some(since), Some(modified)) => {
+            let secs = |time: SystemTime| {
+                time.duration_since(UNIX_EPOCH)
+                    .map_or(0, |since_epoch| since_epoch.as_secs())
+            };
+            secs(modified) <= secs(since)
+        }
+        _ => false,
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum ByteRange {
+    Full,
+    // 包含两端
+    Partial(u64, u64),
+    Unsatisfiable,
+}
+
+impl ByteRange {
+    /// Parse a `Range` header. Headers with several ranges or that can't be
+    /// parsed are ignored and the whole file is served.
+    fn parse(header: &HeaderValue, len: u64) -> Self {
+        let range = match header
+            .to_str()
+            .ok()
+            .and_then(|range| range.trim().strip_prefix("bytes="))
+        {
+            Some(range) if !range.contains(',') => range.trim(),
+            _ => return Self::Full,
+        };
+
+        let (start, end) = match range.split_once('-') {
+            Some(range) => range,
+            None => return Self::Full,
+        };
+
+        if start.is_empty() {
+            // `bytes=-500` 表示最后 500 个字节
+            return match end.parse::<u64>() {
+                Ok(0) => Self::Unsatisfiable,
+                Ok(_) if len == 0 => Self::Unsatisfiable,
+                Ok(suffix) => Self::Partial(len.saturating_sub(suffix), len - 1),
+                Err(_) => Self::Full,
+            };
+        }
+
+        let start = match start.parse::<u64>() {
+            Ok(start) => start,
+            Err(_) => return Self::Full,
+        };
+
+        let end = if end.is_empty() {
+            len.saturating_sub(1)
+        } else {
+            match end.parse::<u64>() {
+                Ok(end) if end >= start => end.min(len.saturating_sub(1)),
+                _ => return Self::Full,
+            }
+        };
+
+        if start >= len {
+            Self::Unsatisfiable
+        } else {
+            Self::Partial(start, end)
+        }
+    }
+}
+
+fn io_error_response(err: io::Error) -> Response<BoxBody> {
+    match err.kind() {
+        io::ErrorKind::NotFound => empty_response(StatusCode::NOT_FOUND),
+        io::ErrorKind::PermissionDenied => empty_response(StatusCode::FORBIDDEN),
+        _ => empty_response(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
+
+fn redirect_to_dir(path: &str, query: Option<&str>) -> Response<BoxBody> {
+    let location = match query {
+        Some(query) => format!("{}/?{}", path, query),
+        None => format!("{}/", path),
+    };
+
+    match HeaderValue::from_str(&location) {
+        Ok(location) => {
+            let mut res = empty_response(StatusCode::PERMANENT_REDIRECT);
+            res.headers_mut().insert(header::LOCATION, location);
+            res
+        }
+        Err(_) => empty_response(StatusCode::NOT_FOUND),
+    }
+}
+
+fn empty_response(status: StatusCode) -> Response<BoxBody> {
+    let mut res = Response::new(crate::body::empty());
+    *res.status_mut() = status;
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ByteRange;
+    use crate::{
+        http::{HeaderValue, StatusCode},
+        service::ServeDir,
+        test_client::TestClient,
+        Router,
+    };
+    use std::{fs, path::PathBuf};
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("serve-dir-{}-{}", name, std::process::id()));
+        fs::create_dir_all(dir.join("docs")).unwrap();
+        fs::write(dir.join("docs/index.html"), "docs").unwrap();
+        fs::write(dir.join("app.css"), "body {}").unwrap();
+        fs::write(dir.join("digits.txt"), "0123456789").unwrap();
+        fs::write(dir.join("blob.unknown-ext"), "blob").unwrap();
+        dir
+    }
+
+    #[tokio::test]
+    async fn redirects_directories_to_trailing_slash() {
+        let dir = temp_dir("redirect");
+        let client = TestClient::new(Router::new().nest("/static", ServeDir::new(&dir)));
+
+        client
+            .get("/static/docs")
+            .send()
+            .await
+            .assert_status(StatusCode::PERMANENT_REDIRECT)
+            .assert_header("location", "/static/docs/");
+
+        client
+            .get("/static/docs?page=2")
+            .send()
+            .await
+            .assert_status(StatusCode::PERMANENT_REDIRECT)
+            .assert_header("location", "/static/docs/?page=2");
+
+        // 嵌套前缀本身也是目录
+        client
+            .get("/static")
+            .send()
+            .await
+            .assert_status(StatusCode::PERMANENT_REDIRECT)
+            .assert_header("location", "/static/");
+
+        client
+            .get("/static/docs/")
+            .send()
+            .await
+            .assert_status(StatusCode::OK)
+            .assert_text("docs");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn rejects_escaping_paths() {
+        let dir = temp_dir("escape");
+        let client = TestClient::new(Router::new().nest("/static", ServeDir::new(&dir)));
+
+        client
+            .get("/static/app.css")
+            .send()
+            .await
+            .assert_status(StatusCode::OK)
+            .assert_text("body {}");
+
+        for path in ["/static/../Cargo.toml", "/static/%2e%2e/Cargo.toml", "/static/app.css%00.txt"] {
+            client.get(path).send().await.assert_status(StatusCode::NOT_FOUND);
+        }
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn guesses_content_type() {
+        let dir = temp_dir("content-type");
+        let client = TestClient::new(Router::new().nest("/static", ServeDir::new(&dir)));
+
+        for (path, content_type) in [
+            ("/static/app.css", "text/css"),
+            ("/static/docs/", "text/html"),
+            ("/static/digits.txt", "text/plain"),
+            ("/static/blob.unknown-ext", "application/octet-stream"),
+        ] {
+            client
+                .get(path)
+                .send()
+                .await
+                .assert_status(StatusCode::OK)
+                .assert_header("content-type", content_type);
+        }
+
+        client
+            .head("/static/app.css")
+            .send()
+            .await
+            .assert_status(StatusCode::OK)
+            .assert_header("content-type", "text/css")
+            .assert_header("content-length", "7")
+            .assert_text("");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn if_none_match() {
+        let dir = temp_dir("etag");
+        let client = TestClient::new(Router::new().nest("/static", ServeDir::new(&dir)));
+
+        let res = client.get("/static/app.css").send().await;
+        let etag = res.header("etag").unwrap().to_owned();
+
+        for if_none_match in [
+            etag.clone(),
+            format!("W/{}", etag),
+            format!("\"other\", {}", etag),
+            "*".to_owned(),
+        ] {
+            client
+                .get("/static/app.css")
+                .header("if-none-match", if_none_match)
+                .send()
+                .await
+                .assert_status(StatusCode::NOT_MODIFIED)
+                .assert_header("etag", &etag)
+                .assert_text("");
+        }
+
+        client
+            .get("/static/app.css")
+            .header("if-none-match", "\"other\"")
+            .send()
+            .await
+            .assert_status(StatusCode::OK)
+            .assert_text("body {}");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn if_modified_since() {
+        let dir = temp_dir("modified");
+        let client = TestClient::new(Router::new().nest("/static", ServeDir::new(&dir)));
+
+        let res = client.get("/static/app.css").send().await;
+        let last_modified = res.header("last-modified").unwrap().to_owned();
+
+        client
+            .get("/static/app.css")
+            .header("if-modified-since", &*last_modified)
+            .send()
+            .await
+            .assert_status(StatusCode::NOT_MODIFIED)
+            .assert_header("last-modified", &last_modified);
+
+        client
+            .get("/static/app.css")
+            .header("if-modified-since", "Mon, 01 Jan 2001 00:00:00 GMT")
+            .send()
+            .await
+            .assert_status(StatusCode::OK)
+            .assert_text("body {}");
+
+        // `If-None-Match` 优先
+        client
+            .get("/static/app.css")
+            .header("if-modified-since", &*last_modified)
+            .header("if-none-match", "\"other\"")
+            .send()
+            .await
+            .assert_status(StatusCode::OK);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn ranges() {
+        let dir = temp_dir("range");
+        let client = TestClient::new(Router::new().nest("/static", ServeDir::new(&dir)));
+
+        for (range, content_range, text) in [
+            ("bytes=2-4", "bytes 2-4/10", "234"),
+            ("bytes=7-", "bytes 7-9/10", "789"),
+            ("bytes=-3", "bytes 7-9/10", "789"),
+            ("bytes=8-100", "bytes 8-9/10", "89"),
+        ] {
+            client
+                .get("/static/digits.txt")
+                .header("range", range)
+                .send()
+                .await
+                .assert_status(StatusCode::PARTIAL_CONTENT)
+                .assert_header("content-range", content_range)
+                .assert_header("content-length", &text.len().to_string())
+                .assert_header("accept-ranges", "bytes")
+                .assert_text(text);
+        }
+
+        client
+            .get("/static/digits.txt")
+            .header("range", "bytes=10-")
+            .send()
+            .await
+            .assert_status(StatusCode::RANGE_NOT_SATISFIABLE)
+            .assert_header("content-range", "bytes */10");
+
+        // 多个范围不支持，返回整个文件
+        client
+            .get("/static/digits.txt")
+            .header("range", "bytes=0-1,4-5")
+            .send()
+            .await
+            .assert_status(StatusCode::OK)
+            .assert_text("0123456789");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn parse_byte_range() {
+        let parse = |range: &'static str, len| {
+            ByteRange::parse(&HeaderValue::from_static(range), len)
+        };
+
+        assert_eq!(parse("bytes=0-0", 10), ByteRange::Partial(0, 0));
+        assert_eq!(parse("bytes=2-4", 10), ByteRange::Partial(2, 4));
+        assert_eq!(parse(" bytes= 2-4 ", 10), ByteRange::Partial(2, 4));
+        assert_eq!(parse("bytes=5-", 10), ByteRange::Partial(5, 9));
+        assert_eq!(parse("bytes=5-20", 10), ByteRange::Partial(5, 9));
+        assert_eq!(parse("bytes=-4", 10), ByteRange::Partial(6, 9));
+        assert_eq!(parse("bytes=-20", 10), ByteRange::Partial(0, 9));
+
+        assert_eq!(parse("bytes=10-", 10), ByteRange::Unsatisfiable);
+        assert_eq!(parse("bytes=10-12", 10), ByteRange::Unsatisfiable);
+        assert_eq!(parse("bytes=-0", 10), ByteRange::Unsatisfiable);
+        assert_eq!(parse("bytes=-5", 0), ByteRange::Unsatisfiable);
+        assert_eq!(parse("bytes=0-", 0), ByteRange::Unsatisfiable);
+
+        // 解析不了的都忽略
+        assert_eq!(parse("bytes=4-2", 10), ByteRange::Full);
+        assert_eq!(parse("bytes=0-1,4-5", 10), ByteRange::Full);
+        assert_eq!(parse("bytes=a-b", 10), ByteRange::Full);
+        assert_eq!(parse("bytes=5", 10), ByteRange::Full);
+        assert_eq!(parse("items=0-5", 10), ByteRange::Full);
+    }
+}
@@ -0,0 +1,475 @@
+//! Calling a router in tests without a server.
+//!
+//! Only available with the `test-client` feature, which is usually enabled
+//! just for tests:
+//!
+//! ```toml
+//! [dev-dependencies]
+//! axumlike02 = { version = "0.1", features = ["test-client"] }
+//! ```
+//!
+//! ```rust,no_run
+//! use axumlike02::{handler::get, http::StatusCode, test_client::TestClient, Router};
+//!
+//! # async {
+//! let app = Router::new().route("/", get(|| async { "Hello, World!" }));
+//! let client = TestClient::new(app);
+//!
+//! let res = client.get("/").header("accept", "text/plain").send().await;
+//! res.assert_status(StatusCode::OK);
+//! assert_eq!(res.text(), "Hello, World!");
+//! # };
+//! ```
+
+use crate::{body::Body, BoxError};
+use bytes::Bytes;
+use http::{
+    header::{self, HeaderName},
+    request, HeaderMap, HeaderValue, Method, Request, Response, StatusCode,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{convert::TryFrom, fmt};
+use tower::ServiceExt;
+use tower_service::Service;
+
+/// A client that sends requests straight to a service, usually a
+/// [`Router`](crate::Router), by calling it.
+///
+/// No sockets are involved, so tests can run in parallel and don't need a
+/// free port. See the [module docs](self) for an example.
+#[derive(Debug, Clone)]
+pub struct TestClient<S> {
+    svc: S,
+}
+
+impl<S, B> TestClient<S>
+where
+    S: Service<Request<Body>, Response = Response<B>> + Clone,
+    S::Error: fmt::Debug,
+    B: http_body::Body<Data = Bytes>,
+    B::Error: Into<BoxError>,
+{
+    /// Create a new `TestClient` sending requests to `svc`.
+    pub fn new(svc: S) -> Self {
+        Self { svc }
+    }
+
+    /// Start building a `GET` request to `path`.
+    pub fn get(&self, path: &str) -> RequestBuilder<S> {
+        self.request(Method::GET, path)
+    }
+
+    /// Start building a `HEAD` request to `path`.
+    pub fn head(&self, path: &str) -> RequestBuilder<S> {
+        self.request(Method::HEAD, path)
+    }
+
+    /// Start building a `POST` request to `path`.
+    pub fn post(&self, path: &str) -> RequestBuilder<S> {
+        self.request(Method::POST, path)
+    }
+
+    /// Start building a `PUT` request to `path`.
+    pub fn put(&self, path: &str) -> RequestBuilder<S> {
+        self.request(Method::PUT, path)
+    }
+
+    /// Start building a `PATCH` request to `path`.
+    pub fn patch(&self, path: &str) -> RequestBuilder<S> {
+        self.request(Method::PATCH, path)
+    }
+
+    /// Start building a `DELETE` request to `path`.
+    pub fn delete(&self, path: &str) -> RequestBuilder<S> {
+        self.request(Method::DELETE, path)
+    }
+
+    /// Start building a request to `path` with any method.
+    ///
+    /// `path` may contain a query string, more query params can be added with
+    /// [`RequestBuilder::query`].
+    pub fn request(&self, method: Method, path: &str) -> RequestBuilder<S> {
+        RequestBuilder {
+            svc: self.svc.clone(),
+            builder: Request::builder().method(method),
+            path: path.to_owned(),
+            body: Body::empty(),
+        }
+    }
+}
+
+/// A request being built by a [`TestClient`].
+///
+/// The methods panic if their arguments can't be used in a request, since
+/// that is a mistake in the test.
+#[derive(Debug)]
+pub struct RequestBuilder<S> {
+    svc: S,
+    builder: request::Builder,
+    path: String,
+    body: Body,
+}
+
+impl<S, B> RequestBuilder<S>
+where
+    S: Service<Request<Body>, Response = Response<B>>,
+    S::Error: fmt::Debug,
+    B: http_body::Body<Data = Bytes>,
+    B::Error: Into<BoxError>,
+{
+    /// Add a header to the request.
+    pub fn header<K, V>(mut self, key: K, value: V) -> Self
+    where
+        HeaderName: TryFrom<K>,
+        <HeaderName as TryFrom<K>>::Error: Into<http::Error>,
+        HeaderValue: TryFrom<V>,
+        <HeaderValue as TryFrom<V>>::Error: Into<http::Error>,
+    {
+        self.builder = self.builder.header(key, value);
+        self
+    }
+
+    /// Add the fields of `query` to the query string of the request.
+    pub fn query<T>(mut self, query: &T) -> Self
+    where
+        T: Serialize + ?Sized,
+    {
+        let query = serde_urlencoded::to_string(query).expect("failed to serialize query");
+
+        if !query.is_empty() {
+            // 路径中可能已经带有查询参数
+            let separator = if self.path.contains('?') { '&' } else { '?' };
+            self.path.push(separator);
+            self.path.push_str(&query);
+        }
+
+        self
+    }
+
+    /// Set the body of the request.
+    pub fn body<T>(mut self, body: T) -> Self
+    where
+        T: Into<Body>,
+    {
+        self.body = body.into();
+        self
+    }
+
+    /// Send `value` serialized as JSON, with a `Content-Type` of
+    /// `application/json`.
+    pub fn json<T>(self, value: &T) -> Self
+    where
+        T: Serialize + ?Sized,
+    {
+        let body = serde_json::to_vec(value).expect("failed to serialize JSON body");
+
+        self.header(header::CONTENT_TYPE, "application/json")
+            .body(body)
+    }
+
+    /// Send `value` serialized as a form, with a `Content-Type` of
+    /// `application/x-www-form-urlencoded`.
+    pub fn form<T>(self, value: &T) -> Self
+    where
+        T: Serialize + ?Sized,
+    {
+        let body = serde_urlencoded::to_string(value).expect("failed to serialize form body");
+
+        self.header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .body(body)
+    }
+
+    /// Send the request and read the whole response.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the request is invalid, or if the service or the response
+    /// body fails.
+    pub async fn send(self) -> TestResponse {
+        let req = self
+            .builder
+            .uri(self.path)
+            .body(self.body)
+            .expect("failed to build request");
+
+        let res = self
+            .svc
+            .oneshot(req)
+            .await
+            .expect("service returned an error");
+
+        let (parts, body) = res.into_parts();
+        let body = match hyper::body::to_bytes(body).await {
+            Ok(body) => body,
+            Err(err) => panic!("failed to read response body: {}", BoxError::from(err.into())),
+        };
+
+        TestResponse {
+            status: parts.status,
+            headers: parts.headers,
+            body,
+        }
+    }
+}
+
+/// A response received by a [`TestClient`].
+///
+/// The assertion methods panic with a message showing the response when they
+/// fail, and return the response so they can be chained.
+#[derive(Debug, Clone)]
+pub struct TestResponse {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Bytes,
+}
+
+impl TestResponse {
+    /// The status code of the response.
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    /// The headers of the response.
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    /// The value of the header `key`, if it's present and valid UTF-8.
+    pub fn header(&self, key: impl header::AsHeaderName) -> Option<&str> {
+        self.headers.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    /// The body of the response.
+    pub fn bytes(&self) -> &Bytes {
+        &self.body
+    }
+
+    /// The body of the response as text, replacing invalid UTF-8.
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+
+    /// Deserialize the body of the response from JSON.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the body isn't valid JSON for `T`.
+    pub fn json<T>(&self) -> T
+    where
+        T: DeserializeOwned,
+    {
+        match serde_json::from_slice(&self.body) {
+            Ok(value) => value,
+            Err(err) => panic!("failed to deserialize JSON body: {}\n{}", err, self.text()),
+        }
+    }
+
+    /// Assert that the response has status `status`.
+    #[track_caller]
+    pub fn assert_status(&self, status: StatusCode) -> &Self {
+        assert_eq!(
+            self.status,
+            status,
+            "unexpected status, response body: {}",
+            self.text()
+        );
+        self
+    }
+
+    /// Assert that the header `key` of the response is `value`.
+    #[track_caller]
+    pub fn assert_header(&self, key: &str, value: &str) -> &Self {
+        assert_eq!(
+            self.header(key),
+            Some(value),
+            "unexpected value for header `{}`, headers: {:?}",
+            key,
+            self.headers
+        );
+        self
+    }
+
+    /// Assert that the response body is `text`.
+    #[track_caller]
+    pub fn assert_text(&self, text: &str) -> &Self {
+        assert_eq!(self.text(), text, "unexpected response body");
+        self
+    }
+
+    /// Assert that the response body is the JSON `value`.
+    #[track_caller]
+    pub fn assert_json<T>(&self, value: &T) -> &Self
+    where
+        T: DeserializeOwned + PartialEq + fmt::Debug,
+    {
+        assert_eq!(&self.json::<T>(), value, "unexpected JSON response body");
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{handler::on, router::MethodFilter, Json, Router};
+    use serde::Deserialize;
+    use std::collections::HashMap;
+
+    #[derive(Debug, PartialEq, Deserialize, Serialize)]
+    struct Echo {
+        method: String,
+        uri: String,
+        content_type: Option<String>,
+        body: String,
+    }
+
+    async fn echo(method: Method, uri: http::Uri, headers: HeaderMap, body: String) -> Json<Echo> {
+        Json(Echo {
+            method: method.to_string(),
+            uri: uri.to_string(),
+            content_type: headers
+                .get(header::CONTENT_TYPE)
+                .map(|value| value.to_str().unwrap().to_owned()),
+            body,
+        })
+    }
+
+    #[tokio::test]
+    async fn sends_method_path_and_body() {
+        let client = TestClient::new(Router::new().route("/echo", on(MethodFilter::all(), echo)));
+
+        let res = client.put("/echo").body("hello").send().await;
+        res.assert_status(StatusCode::OK)
+            .assert_header("content-type", "application/json");
+        assert_eq!(
+            res.json::<Echo>(),
+            Echo {
+                method: "PUT".to_owned(),
+                uri: "/echo".to_owned(),
+                content_type: None,
+                body: "hello".to_owned(),
+            }
+        );
+
+        for (method, res) in [
+            ("GET", client.get("/echo").send().await),
+            ("POST", client.post("/echo").send().await),
+            ("PATCH", client.patch("/echo").send().await),
+            ("DELETE", client.delete("/echo").send().await),
+            (
+                "OPTIONS",
+                client.request(Method::OPTIONS, "/echo").send().await,
+            ),
+        ] {
+            assert_eq!(res.json::<Echo>().method, method);
+        }
+
+        // `HEAD` 响应没有响应体，但头部还在
+        let res = client.head("/echo").send().await;
+        res.assert_status(StatusCode::OK)
+            .assert_header("content-type", "application/json");
+        assert!(res.bytes().is_empty());
+
+        client
+            .get("/missing")
+            .send()
+            .await
+            .assert_status(StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn appends_query_params() {
+        let client = TestClient::new(Router::new().route("/echo", on(MethodFilter::all(), echo)));
+
+        let mut query = HashMap::new();
+        query.insert("a", "1");
+        let res = client.get("/echo").query(&query).send().await;
+        assert_eq!(res.json::<Echo>().uri, "/echo?a=1");
+
+        let res = client.get("/echo?a=1").query(&[("b", "2 3")]).send().await;
+        assert_eq!(res.json::<Echo>().uri, "/echo?a=1&b=2+3");
+
+        let empty: [(&str, &str); 0] = [];
+        let res = client.get("/echo").query(&empty).send().await;
+        assert_eq!(res.json::<Echo>().uri, "/echo");
+    }
+
+    #[tokio::test]
+    async fn json_and_form_bodies() {
+        let client = TestClient::new(Router::new().route("/echo", on(MethodFilter::all(), echo)));
+
+        let res = client.post("/echo").json(&[("a", 1)]).send().await;
+        let echo = res.json::<Echo>();
+        assert_eq!(echo.content_type.as_deref(), Some("application/json"));
+        assert_eq!(echo.body, r#"[["a",1]]"#);
+
+        let res = client
+            .post("/echo")
+            .form(&[("a", "1"), ("b", "2")])
+            .send()
+            .await;
+        let echo = res.json::<Echo>();
+        assert_eq!(
+            echo.content_type.as_deref(),
+            Some("application/x-www-form-urlencoded")
+        );
+        assert_eq!(echo.body, "a=1&b=2");
+
+        let res = client
+            .post("/echo")
+            .header(header::CONTENT_TYPE, "text/plain")
+            .body("hi")
+            .send()
+            .await;
+        let echo = res.json::<Echo>();
+        assert_eq!(echo.content_type.as_deref(), Some("text/plain"));
+        assert_eq!(echo.body, "hi");
+    }
+
+    #[tokio::test]
+    async fn response_accessors_and_assertions() {
+        let client = TestClient::new(Router::new().route("/echo", on(MethodFilter::all(), echo)));
+
+        let res = client.post("/echo").body("hi").send().await;
+
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.header("content-type"), Some("application/json"));
+        assert_eq!(res.header("x-missing"), None);
+        assert_eq!(res.bytes().as_ref(), res.text().as_bytes());
+        res.assert_json(&Echo {
+            method: "POST".to_owned(),
+            uri: "/echo".to_owned(),
+            content_type: None,
+            body: "hi".to_owned(),
+        });
+    }
+
+    #[tokio::test]
+    #[should_panic(expected = "unexpected status")]
+    async fn assert_status_panics() {
+        TestClient::new(Router::new().route("/echo", on(MethodFilter::all(), echo)))
+            .get("/missing")
+            .send()
+            .await
+            .assert_status(StatusCode::OK);
+    }
+
+    #[tokio::test]
+    #[should_panic(expected = "unexpected value for header `x-missing`")]
+    async fn assert_header_panics() {
+        TestClient::new(Router::new().route("/echo", on(MethodFilter::all(), echo)))
+            .get("/echo")
+            .send()
+            .await
+            .assert_header("x-missing", "value");
+    }
+
+    #[tokio::test]
+    #[should_panic(expected = "failed to deserialize JSON body")]
+    async fn json_panics_on_invalid_body() {
+        TestClient::new(Router::new().route("/echo", on(MethodFilter::all(), echo)))
+            .get("/missing")
+            .send()
+            .await
+            .json::<Echo>();
+    }
+}